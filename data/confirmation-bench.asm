@@ -0,0 +1,28 @@
+# The teleporter confirmation routine of the challenge, called with AX = 4, BX = 1 and HX = 1. It
+# takes far longer to return than any sensible instruction budget, which makes it a benchmark of
+# the raw speed of the VM. See `synacor-vm bench`.
+
+#init
+0x0000:     SET    AX      0x0004
+0x0003:     SET    BX      0x0001
+0x0006:     SET    HX      0x0001
+0x0009:    CALL    0x000C
+0x000B:    HALT
+
+#confirm
+0x000C:      JT    AX      0x0014
+0x000F:     ADD    AX      BX      0x0001
+0x0013:     RET
+0x0014:      JT    BX      0x0021
+0x0017:     ADD    AX      AX      0x7FFF
+0x001B:     SET    BX      HX
+0x001E:    CALL    0x000C
+0x0020:     RET
+0x0021:    PUSH    AX
+0x0023:     ADD    BX      BX      0x7FFF
+0x0027:    CALL    0x000C
+0x0029:     SET    BX      AX
+0x002C:     POP    AX
+0x002E:     ADD    AX      AX      0x7FFF
+0x0032:    CALL    0x000C
+0x0034:     RET
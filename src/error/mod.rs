@@ -13,10 +13,16 @@ use crate::constants::*;
 pub enum Error {
     BadBytecodeFormat,
     BadBytecodeLength(usize),
+    BadTraceFormat,
+    BadTranscriptFormat,
     InvalidOperation { pc: usize, operation: u16 },
     InvalidRegister { pc: usize, register: u16 },
     MemOutOfBoundsAccess { pc: usize },
     PopFromEmptyStack { pc: usize },
+    DivisionByZero { pc: usize },
+    InvalidLiteral { pc: usize, value: u16 },
+    StackUnderflowOnRet { pc: usize },
+    NonAsciiOutput { pc: usize, value: u16 },
     ReadInputErr { pc: usize },
     DeserializeErr { pc: usize, error: ron::Error },
     SerializeErr { pc: usize, error: ron::Error },
@@ -33,6 +39,8 @@ impl Display for Error {
                 "Bytecode of length {} exceeds memory address space of {}.",
                 x, MEM_ADDR_SPACE
             ),
+            Error::BadTraceFormat => write!(f, "Invalid or truncated trace file."),
+            Error::BadTranscriptFormat => write!(f, "Invalid transcript file."),
             Error::InvalidOperation { pc, operation } => {
                 write!(f, "Invalid operation {:#06x} at {:#06x}.", operation, pc)
             }
@@ -51,6 +59,18 @@ impl Display for Error {
                 "Attempted to pop value out of empty stack at {:#06x}.",
                 pc
             ),
+            Error::DivisionByZero { pc } => write!(f, "Attempted division by zero at {:#06x}.", pc),
+            Error::InvalidLiteral { pc, value } => {
+                write!(f, "Invalid literal value {:#06x} at {:#06x}.", value, pc)
+            }
+            Error::StackUnderflowOnRet { pc } => {
+                write!(f, "Attempted to return with an empty stack at {:#06x}.", pc)
+            }
+            Error::NonAsciiOutput { pc, value } => write!(
+                f,
+                "Attempted to output non-ASCII value {:#06x} at {:#06x}.",
+                value, pc
+            ),
             Error::ReadInputErr { pc } => {
                 write!(f, "Could not read user input from stdin at {:#06x}.", pc)
             }
@@ -4,7 +4,9 @@
 //! the challenge is located in `instructions`.
 
 extern crate ron;
+extern crate rustyline;
 extern crate serde;
+extern crate serde_json;
 
 pub mod assembler;
 pub mod teleporter;
@@ -18,4 +20,12 @@ mod vm;
 pub type Result<T> = std::result::Result<T, error::Error>;
 
 pub use error::Error;
-pub use vm::VirtualMachine;
+pub use vm::{
+    budget::Budget,
+    call_graph::{CallEdge, CallGraph},
+    coverage::Coverage,
+    debugger::{Breakpoint, Breakpoints, WatchHit, WatchKind, Watchpoint, Watchpoints},
+    intrinsic, io, profiler, self_modify,
+    snapshot::Snapshot,
+    trace, transcript, ReverseOutcome, RunOutcome, StepOutcome, VirtualMachine,
+};
@@ -1,24 +1,120 @@
+extern crate similar;
 extern crate synacor_vm;
 
-use std::{env, fmt, fs, process};
-use synacor_vm::{assembler, teleporter, vault, Result, VirtualMachine};
+use similar::TextDiff;
+use std::{
+    env, fmt, fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+use synacor_vm::{
+    assembler,
+    io::{
+        ChainInput, EditorInput, FileInput, InputSource, MemoryInput, MemoryOutput, NullOutput,
+        StdinInput,
+    },
+    self_modify::SelfModifyMode,
+    teleporter, trace,
+    transcript::TranscriptInput,
+    vault, Budget, Result, RunOutcome, VirtualMachine,
+};
+
+/// Exit code when the VM stops because its input ran out.
+const EXIT_INPUT_EXHAUSTED: i32 = 2;
+/// Exit code when the VM stops because its instruction budget or time limit ran out.
+const EXIT_BUDGET_EXHAUSTED: i32 = 3;
+/// Instructions executed by each run of bench, unless given with --max-instructions.
+const BENCH_INSTRUCTIONS: u64 = 100_000_000;
+/// File in the home directory keeping the history of lines typed at the prompt.
+const HISTORY_FILE: &str = ".synacor-vm-history";
 
 #[derive(Default)]
 struct Options {
     out: Option<String>,
     asm_addresses: bool,
+    trace: Option<String>,
+    profile: Option<String>,
+    call_graph: Option<String>,
+    coverage: Option<String>,
+    self_modify: Option<String>,
+    max_instructions: Option<u64>,
+    time_limit: Option<Duration>,
+    strict: bool,
+    command_prefix: Option<String>,
+    input: Option<String>,
+    replay: Option<String>,
+    record: Option<String>,
+    stop_after_input: bool,
+    update: bool,
 }
 
 impl Options {
     fn from_args() -> Self {
         let mut opts = Options::default();
+        let mut args = env::args();
 
-        for arg in env::args() {
+        while let Some(arg) = args.next() {
             match arg.as_ref() {
                 x if x.starts_with("--out=") => opts.out = Some(x.chars().skip(6).collect()),
+                x if x.starts_with("--trace=") => opts.trace = Some(x.chars().skip(8).collect()),
+                "--trace" => match args.next() {
+                    Some(x) => opts.trace = Some(x),
+                    None => print_err_usage("No <tracefile> given for --trace."),
+                },
+                "--profile" => match args.next() {
+                    Some(x) => opts.profile = Some(x),
+                    None => print_err_usage("No <file> given for --profile."),
+                },
+                "--callgraph" => match args.next() {
+                    Some(x) => opts.call_graph = Some(x),
+                    None => print_err_usage("No <file> given for --callgraph."),
+                },
+                "--coverage" => match args.next() {
+                    Some(x) => opts.coverage = Some(x),
+                    None => print_err_usage("No <file> given for --coverage."),
+                },
+                "--self-modify" => match args.next() {
+                    Some(x) => opts.self_modify = Some(x),
+                    None => print_err_usage("No <file> given for --self-modify."),
+                },
+                "--max-instructions" => match args.next().map(|x| x.parse()) {
+                    Some(Ok(x)) => opts.max_instructions = Some(x),
+                    _ => print_err_usage("No valid <count> given for --max-instructions."),
+                },
+                "--time-limit" => match args.next().map(|x| x.parse()) {
+                    Some(Ok(x)) => opts.time_limit = Some(Duration::from_secs_f64(x)),
+                    _ => print_err_usage("No valid <seconds> given for --time-limit."),
+                },
                 "--with-addresses" => {
                     opts.asm_addresses = true;
                 }
+                "--strict" => {
+                    opts.strict = true;
+                }
+                "--command-prefix" => match args.next() {
+                    Some(x) => opts.command_prefix = Some(x),
+                    None => print_err_usage("No <prefix> given for --command-prefix."),
+                },
+                "--input" => match args.next() {
+                    Some(x) => opts.input = Some(x),
+                    None => print_err_usage("No <script> given for --input."),
+                },
+                "--replay" => match args.next() {
+                    Some(x) => opts.replay = Some(x),
+                    None => print_err_usage("No <transcript> given for --replay."),
+                },
+                "--record" => match args.next() {
+                    Some(x) => opts.record = Some(x),
+                    None => print_err_usage("No <transcript> given for --record."),
+                },
+                "--update" => {
+                    opts.update = true;
+                }
+                "--stop-after-input" => {
+                    opts.stop_after_input = true;
+                }
                 x if x.starts_with("-") => print_err_usage(&format!(
                     "No option \"{}\" exists",
                     x.chars().take_while(|&c| c != '=').collect::<String>()
@@ -40,7 +136,177 @@ fn main() -> Result<()> {
 
         "run" => {
             let bytecode = read_bytecode();
-            VirtualMachine::new().load_bytecode(&bytecode)?.run()?;
+            let opts = Options::from_args();
+            let mut vm = VirtualMachine::new().load_bytecode(&bytecode)?;
+
+            let script: Option<Box<dyn InputSource>> = match (&opts.input, &opts.replay) {
+                (Some(_), Some(_)) => {
+                    print_err_usage("Only one of --input and --replay may be given.")
+                }
+                (Some(script), None) => Some(Box::new(FileInput::open(script)?)),
+                (None, Some(transcript)) => Some(Box::new(TranscriptInput::open(transcript)?)),
+                (None, None) => None,
+            };
+            match (script, opts.stop_after_input) {
+                (Some(script), false) => vm.set_input(ChainInput::new(script, player_input())),
+                (Some(script), true) => vm.set_input(script),
+                (None, _) => vm.set_input(player_input()),
+            }
+            if let Some(transcript) = opts.record {
+                vm.record_transcript(transcript)?;
+            }
+
+            if let Some(tracefile) = opts.trace {
+                vm.trace_to(tracefile)?;
+            }
+            if opts.profile.is_some() {
+                vm.start_profiling();
+            }
+            if opts.call_graph.is_some() {
+                vm.start_call_graph();
+            }
+            if opts.coverage.is_some() {
+                vm.start_coverage();
+            }
+            if opts.self_modify.is_some() {
+                vm.start_self_modify_detection(SelfModifyMode::Warn);
+            }
+            vm.set_strict(opts.strict);
+            if let Some(prefix) = opts.command_prefix {
+                vm.set_command_prefix(Some(prefix).filter(|x| !x.is_empty()));
+            }
+            vm.set_fuel(opts.max_instructions);
+            vm.set_time_limit(opts.time_limit);
+
+            let outcome = vm.run();
+            if let (Some(file), Some(report)) = (opts.profile, vm.profile_report()) {
+                report.write_to_file(file)?;
+            }
+            if let (Some(file), Some(graph)) = (opts.call_graph, vm.call_graph()) {
+                graph.write_to_file(file)?;
+            }
+            if let Some(file) = opts.coverage {
+                fs::write(file, vm.disassemble(true, true))?;
+            }
+            if let (Some(file), Some(report)) = (opts.self_modify, vm.self_modify_report()) {
+                report.write_to_file(file)?;
+            }
+
+            let code = match outcome? {
+                RunOutcome::Halted | RunOutcome::RetOnEmptyStack | RunOutcome::UserExit => 0,
+                RunOutcome::InputExhausted => {
+                    eprintln!("Input exhausted at {:#06x}.", vm.pc());
+                    EXIT_INPUT_EXHAUSTED
+                }
+                RunOutcome::BudgetExhausted(budget) => {
+                    let limit = match budget {
+                        Budget::Instructions => "Instruction budget",
+                        Budget::Time => "Time limit",
+                    };
+                    eprintln!(
+                        "{} exhausted after {} instructions at {:#06x}.",
+                        limit,
+                        vm.instruction_count(),
+                        vm.pc()
+                    );
+                    EXIT_BUDGET_EXHAUSTED
+                }
+            };
+            if code != 0 {
+                process::exit(code);
+            }
+        }
+
+        "check" => {
+            let bytecode = read_bytecode();
+            let (script, expected) = match (env::args().nth(3), env::args().nth(4)) {
+                (Some(script), Some(expected)) => (script, expected),
+                _ => print_err_usage("No <script> and <expected> supplied."),
+            };
+            let opts = Options::from_args();
+            let output = MemoryOutput::new();
+            let mut vm = VirtualMachine::new()
+                .with_input(FileInput::open(script)?)
+                .with_output(output.clone())
+                .load_bytecode(&bytecode)?;
+
+            vm.set_strict(opts.strict);
+            if let Some(prefix) = opts.command_prefix {
+                vm.set_command_prefix(Some(prefix).filter(|x| !x.is_empty()));
+            }
+            vm.set_fuel(opts.max_instructions);
+            vm.set_time_limit(opts.time_limit);
+
+            let outcome = vm.run();
+            let actual = output.take();
+
+            if opts.update {
+                fs::write(expected, &actual)?;
+                outcome?;
+                return Ok(());
+            }
+
+            let expected_output = fs::read_to_string(&expected)?;
+            if expected_output != actual {
+                let diff = TextDiff::from_lines(&expected_output, &actual);
+
+                print!("{}", diff.unified_diff().header(&expected, "actual output"));
+                process::exit(1);
+            }
+            outcome?;
+        }
+
+        "bench" => {
+            let bytecode = read_bytecode();
+            let opts = Options::from_args();
+            let mut rates = vec![];
+
+            for &decode_cache in [false, true].iter() {
+                let input: Box<dyn InputSource> = match &opts.input {
+                    Some(script) => Box::new(FileInput::open(script)?),
+                    None => Box::new(MemoryInput::new("")),
+                };
+                let mut vm = VirtualMachine::new()
+                    .with_input(input)
+                    .with_output(NullOutput)
+                    .load_bytecode(&bytecode)?;
+
+                vm.set_decode_cache(decode_cache);
+                vm.set_strict(opts.strict);
+                vm.set_fuel(Some(opts.max_instructions.unwrap_or(BENCH_INSTRUCTIONS)));
+
+                let start = Instant::now();
+                vm.run()?;
+                let secs = start.elapsed().as_secs_f64();
+                let rate = vm.instruction_count() as f64 / secs;
+
+                println!(
+                    "{:<22}{:>12} instructions in {:>7.3}s  {:>7.2}M/s",
+                    if decode_cache {
+                        "With decode cache:"
+                    } else {
+                        "Without decode cache:"
+                    },
+                    vm.instruction_count(),
+                    secs,
+                    rate / 1e6
+                );
+                rates.push(rate);
+            }
+            println!("Speedup: {:.2}x", rates[1] / rates[0]);
+        }
+
+        "trace" => {
+            let tracefile = match env::args().nth(2) {
+                Some(x) => x,
+                None => print_err_usage("No <tracefile> supplied."),
+            };
+            let opts = Options::from_args();
+
+            match opts.out {
+                Some(outfile) => trace::render(tracefile, &mut fs::File::create(outfile)?)?,
+                None => trace::render(tracefile, &mut io::stdout().lock())?,
+            };
         }
 
         "assemble" => {
@@ -58,7 +324,7 @@ fn main() -> Result<()> {
             let opts = Options::from_args();
             let asm = VirtualMachine::new()
                 .load_bytecode(&bytecode)?
-                .disassemble(opts.asm_addresses);
+                .disassemble(opts.asm_addresses, false);
 
             match opts.out {
                 Some(outfile) => fs::write(outfile, &asm)?,
@@ -112,16 +378,100 @@ fn print_usage(print_header: bool) {
     }
 
     println!("Usage:");
-    println!("    synacor-vm help                            Print this usage information");
-    println!("    synacor-vm run <infile>                    Run compiled synacor binary");
-    println!("    synacor-vm assemble <infile> <outfile>     Assemble synacor asm into binary");
-    println!("    synacor-vm disassemble <infile> [options]  Disassemble compiled synacor binary");
-    println!("    synacor-vm solve-calibration [value]       Solve calibration for HX register");
-    println!("    synacor-vm solve-vault [max_depth]         Solve vault access path");
+    println!("    synacor-vm help                                Print this usage information");
+    println!("    synacor-vm run <infile> [options]              Run compiled synacor binary");
+    println!("    synacor-vm assemble <infile> <outfile>         Assemble synacor asm into binary");
+    println!(
+        "    synacor-vm disassemble <infile> [options]      Disassemble compiled synacor binary"
+    );
+    println!("    synacor-vm check <infile> <script> <expected>  Check output of a scripted run");
+    println!(
+        "    synacor-vm bench <infile> [options]            Measure the speed of the decode cache"
+    );
+    println!(
+        "    synacor-vm trace <tracefile> [options]         Render an execution trace as text"
+    );
+    println!(
+        "    synacor-vm solve-calibration [value]           Solve calibration for HX register"
+    );
+    println!("    synacor-vm solve-vault [max_depth]             Solve vault access path");
     println!();
     println!("Options:");
-    println!("    --out=<outfile>   Write to a given output file instead of stdout");
-    println!("    --with-addresses  Specify that the assembly should be addressed");
+    println!("    --out=<outfile>             Write to a given output file instead of stdout");
+    println!("    --with-addresses            Specify that the assembly should be addressed");
+    println!("    --trace <file>              Record every executed instruction to a trace file");
+    println!(
+        "    --profile <file>            Write an execution profile (JSON if <file> is .json)"
+    );
+    println!(
+        "    --callgraph <file>          Write the call graph as DOT (JSON if <file> is .json)"
+    );
+    println!(
+        "    --coverage <file>           Write a disassembly marking the executed instructions"
+    );
+    println!(
+        "    --self-modify <file>        Warn on self-modifying code and write a report of it"
+    );
+    println!(
+        "    --input <script>            Feed lines from <script> as input before reading stdin"
+    );
+    println!("    --replay <transcript>       Replay a recorded transcript before reading stdin");
+    println!("    --record <transcript>       Record every line of input to a transcript");
+    println!("    --stop-after-input          Stop at the end of the --input or --replay input");
+    println!("    --update                    Write the output of check to <expected> instead");
+    println!("    --strict                    Fail on values and operations outside of the spec");
+    println!(
+        "    --command-prefix <prefix>   Take input lines starting with <prefix> as commands, `!` by"
+    );
+    println!(
+        "                                default, or lines starting with a command name if empty"
+    );
+    println!("    --max-instructions <count>  Stop running after executing <count> instructions");
+    println!("    --time-limit <seconds>      Stop running after <seconds> of wall-clock time");
+    println!();
+    println!("Exit codes of run:");
+    println!("    0  The program halted, or the `exit` command was entered");
+    println!("    1  The VM failed with an error");
+    println!("    2  The input ran out while the program was waiting for input");
+    println!("    3  The instruction budget or time limit ran out");
+    println!();
+    println!(
+        "When run from a terminal, lines typed at the prompt can be edited, completed with Tab"
+    );
+    println!(
+        "and recalled with the arrow keys. The history is kept in ~/{}.",
+        HISTORY_FILE
+    );
+    println!();
+    println!("check exits with 1 if the output differs from <expected>, printing a unified diff.");
+    println!(
+        "data/challenge-walkthrough.txt plays data/challenge.bin to the end, and its expected"
+    );
+    println!("output is data/challenge-walkthrough.out.");
+    println!(
+        "bench runs <infile> for 100000000 instructions unless given --max-instructions, with"
+    );
+    println!(
+        "the decode cache disabled and then enabled. data/confirmation-bench.bin makes a good"
+    );
+    println!("<infile>.");
+}
+
+/// Reads from a line editor with persistent history when stdin is a terminal, and plainly from
+/// stdin otherwise.
+fn player_input() -> Box<dyn InputSource> {
+    if !io::stdin().is_terminal() {
+        return Box::new(StdinInput);
+    }
+
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    match EditorInput::new(history) {
+        Ok(editor) => Box::new(editor),
+        Err(e) => {
+            eprintln!("Warning: line editing unavailable: {}", e);
+            Box::new(StdinInput)
+        }
+    }
 }
 
 fn print_err_usage<M: fmt::Display>(err_msg: M) -> ! {
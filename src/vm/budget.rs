@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+/// How often, in instructions, the wall-clock time limit is checked.
+const DEADLINE_CHECK_INTERVAL: u64 = 0x400;
+
+/// A limit on how long the VirtualMachine may run for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Budget {
+    /// The maximum number of instructions to execute.
+    Instructions,
+    /// The maximum wall-clock time to spend in a single run.
+    Time,
+}
+
+/// Enforces the instruction budget and time limit of the VirtualMachine.
+#[derive(Clone, Default)]
+pub(crate) struct Watchdog {
+    fuel: Option<u64>,
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl Watchdog {
+    /// Sets the number of instructions which may still be executed, or `None` for no limit.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// Gets the number of instructions which may still be executed.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Sets the wall-clock time limit applied to each run, or `None` for no limit.
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.time_limit = time_limit;
+    }
+
+    /// Starts the clock for a run.
+    pub fn start(&mut self) {
+        self.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+    }
+
+    /// Stops the clock once a run is over.
+    pub fn stop(&mut self) {
+        self.deadline = None;
+    }
+
+    /// Checks if a run is under a time limit, which has to be checked as instructions execute.
+    pub fn is_timed(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Checks if any budget is exhausted before executing the next instruction, given the total
+    /// number of instructions executed so far.
+    pub fn check(&self, executed: u64) -> Option<Budget> {
+        if self.fuel == Some(0) {
+            return Some(Budget::Instructions);
+        }
+
+        match self.deadline {
+            Some(deadline)
+                if executed.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                    && Instant::now() >= deadline =>
+            {
+                Some(Budget::Time)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the fuel for a single executed instruction.
+    pub fn consume(&mut self) {
+        if let Some(fuel) = self.fuel.as_mut() {
+            *fuel = fuel.saturating_sub(1);
+        }
+    }
+}
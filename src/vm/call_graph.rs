@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Write, fs, io, path::Path};
+
+/// A `CALL` site and target, along with the routine making the call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct CallSite {
+    caller: Option<u16>,
+    site: u16,
+    target: u16,
+}
+
+/// A call graph recorded from the `CALL` and `RET` instructions executed by the VirtualMachine.
+#[derive(Default)]
+pub struct CallGraph {
+    edges: BTreeMap<CallSite, u64>,
+    frames: Vec<u16>,
+}
+
+/// A single edge of the call graph.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CallEdge {
+    /// The entry address of the routine making the call, or `None` outside of any routine.
+    pub caller: Option<u16>,
+    /// The address of the `CALL` instruction.
+    pub site: u16,
+    /// The address called.
+    pub target: u16,
+    /// The number of times the call was made.
+    pub count: u64,
+}
+
+impl CallGraph {
+    /// Creates an empty call graph.
+    pub fn new() -> Self {
+        CallGraph::default()
+    }
+
+    /// Records a `CALL` from the instruction at `site` to `target`.
+    pub(crate) fn record_call(&mut self, site: usize, target: usize) {
+        let call = CallSite {
+            caller: self.frames.last().cloned(),
+            site: site as u16,
+            target: target as u16,
+        };
+
+        *self.edges.entry(call).or_insert(0) += 1;
+        self.frames.push(target as u16);
+    }
+
+    /// Records a `RET` from the current routine.
+    pub(crate) fn record_ret(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Iterates over every recorded edge, ordered by caller and site.
+    pub fn edges(&self) -> impl Iterator<Item = CallEdge> + '_ {
+        self.edges.iter().map(|(call, &count)| CallEdge {
+            caller: call.caller,
+            site: call.site,
+            target: call.target,
+            count,
+        })
+    }
+
+    /// Renders the call graph in Graphviz DOT format, with a node per routine and an edge per
+    /// call site.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n    node [shape=box, fontname=monospace];\n");
+        let node = |addr: Option<u16>| match addr {
+            Some(x) => format!("\"{:#06x}\"", x),
+            None => "\"entry\"".to_string(),
+        };
+
+        for edge in self.edges() {
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"{:#06x} x{}\"];",
+                node(edge.caller),
+                node(Some(edge.target)),
+                edge.site,
+                edge.count
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the call graph as a JSON list of edges.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.edges().collect::<Vec<_>>()).unwrap_or_default()
+    }
+
+    /// Writes the call graph to a file, as JSON if the path has a `.json` extension and as DOT
+    /// otherwise.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let graph = match path.as_ref().extension() {
+            Some(ext) if ext == "json" => self.to_json(),
+            _ => self.to_dot(),
+        };
+
+        fs::write(path, graph)
+    }
+}
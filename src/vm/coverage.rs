@@ -0,0 +1,120 @@
+use crate::constants::*;
+
+const WORD_BITS: usize = 64;
+
+/// A set of memory addresses, stored as one bit per address.
+#[derive(Clone)]
+pub(crate) struct AddrSet {
+    words: Vec<u64>,
+}
+
+impl AddrSet {
+    pub(crate) fn new() -> Self {
+        AddrSet {
+            words: vec![0; MEM_ADDR_SPACE / WORD_BITS],
+        }
+    }
+
+    pub(crate) fn insert(&mut self, addr: usize) {
+        if let Some(word) = self.words.get_mut(addr / WORD_BITS) {
+            *word |= 1 << (addr % WORD_BITS);
+        }
+    }
+
+    pub(crate) fn contains(&self, addr: usize) -> bool {
+        self.words
+            .get(addr / WORD_BITS)
+            .is_some_and(|word| word & (1 << (addr % WORD_BITS)) != 0)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
+/// Marks which addresses have been executed as opcodes, and which have been read or written as
+/// data.
+#[derive(Clone)]
+pub struct Coverage {
+    executed: AddrSet,
+    read: AddrSet,
+    written: AddrSet,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Coverage::new()
+    }
+}
+
+impl Coverage {
+    /// Creates an empty coverage map.
+    pub fn new() -> Self {
+        Coverage {
+            executed: AddrSet::new(),
+            read: AddrSet::new(),
+            written: AddrSet::new(),
+        }
+    }
+
+    pub(crate) fn mark_executed(&mut self, addr: usize) {
+        self.executed.insert(addr);
+    }
+
+    pub(crate) fn mark_read(&mut self, addr: usize) {
+        self.read.insert(addr);
+    }
+
+    pub(crate) fn mark_written(&mut self, addr: usize) {
+        self.written.insert(addr);
+    }
+
+    /// Checks if an instruction was executed starting at the given address.
+    pub fn is_executed(&self, addr: usize) -> bool {
+        self.executed.contains(addr)
+    }
+
+    /// Checks if the given address was read as data.
+    pub fn is_read(&self, addr: usize) -> bool {
+        self.read.contains(addr)
+    }
+
+    /// Checks if the given address was written as data.
+    pub fn is_written(&self, addr: usize) -> bool {
+        self.written.contains(addr)
+    }
+
+    /// Gets the number of addresses executed as opcodes.
+    pub fn executed_count(&self) -> usize {
+        self.executed.len()
+    }
+
+    /// Gets the number of addresses read as data.
+    pub fn read_count(&self) -> usize {
+        self.read.len()
+    }
+
+    /// Gets the number of addresses written as data.
+    pub fn written_count(&self) -> usize {
+        self.written.len()
+    }
+
+    /// Gets the marker shown next to an address in a coverage disassembly. Instructions are
+    /// marked `+` if executed and `-` if never executed, while data is marked with `r` and `w`
+    /// for reads and writes.
+    pub(crate) fn marker(&self, addr: usize, is_instruction: bool) -> &'static str {
+        if is_instruction {
+            return if self.is_executed(addr) { "+ " } else { "- " };
+        }
+
+        match (self.is_read(addr), self.is_written(addr)) {
+            (true, true) => "rw",
+            (true, false) => "r ",
+            (false, true) => " w",
+            (false, false) => "  ",
+        }
+    }
+}
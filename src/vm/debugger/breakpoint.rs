@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+/// A breakpoint on an instruction address.
+#[derive(Clone, Copy, Debug)]
+pub struct Breakpoint {
+    /// Whether the breakpoint will stop execution.
+    pub enabled: bool,
+    /// The number of times execution has stopped at the breakpoint.
+    pub hits: usize,
+}
+
+/// The set of instruction breakpoints, keyed by address.
+#[derive(Clone, Default)]
+pub struct Breakpoints {
+    points: BTreeMap<usize, Breakpoint>,
+}
+
+impl Breakpoints {
+    /// Creates an empty set of breakpoints.
+    pub fn new() -> Self {
+        Breakpoints::default()
+    }
+
+    /// Adds an enabled breakpoint at the given address. Returns false if one already exists.
+    pub fn add(&mut self, addr: usize) -> bool {
+        if self.points.contains_key(&addr) {
+            return false;
+        }
+
+        self.points.insert(
+            addr,
+            Breakpoint {
+                enabled: true,
+                hits: 0,
+            },
+        );
+        true
+    }
+
+    /// Removes the breakpoint at the given address. Returns false if none exists.
+    pub fn remove(&mut self, addr: usize) -> bool {
+        self.points.remove(&addr).is_some()
+    }
+
+    /// Enables the breakpoint at the given address. Returns false if none exists.
+    pub fn enable(&mut self, addr: usize) -> bool {
+        self.set_enabled(addr, true)
+    }
+
+    /// Disables the breakpoint at the given address without removing it. Returns false if none
+    /// exists.
+    pub fn disable(&mut self, addr: usize) -> bool {
+        self.set_enabled(addr, false)
+    }
+
+    /// Gets the breakpoint at the given address.
+    pub fn get(&self, addr: usize) -> Option<&Breakpoint> {
+        self.points.get(&addr)
+    }
+
+    /// Iterates over all breakpoints in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Breakpoint)> {
+        self.points.iter().map(|(&addr, bp)| (addr, bp))
+    }
+
+    /// Checks if an enabled breakpoint exists at the given address.
+    pub fn is_enabled(&self, addr: usize) -> bool {
+        self.points.get(&addr).is_some_and(|bp| bp.enabled)
+    }
+
+    /// Checks if there are no breakpoints.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Records a hit and returns true if an enabled breakpoint exists at the given address.
+    pub(crate) fn hit(&mut self, addr: usize) -> bool {
+        match self.points.get_mut(&addr) {
+            Some(bp) if bp.enabled => {
+                bp.hits += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn set_enabled(&mut self, addr: usize, enabled: bool) -> bool {
+        match self.points.get_mut(&addr) {
+            Some(bp) => {
+                bp.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+}
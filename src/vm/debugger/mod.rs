@@ -0,0 +1,122 @@
+mod breakpoint;
+mod watchpoint;
+
+pub use self::{
+    breakpoint::{Breakpoint, Breakpoints},
+    watchpoint::{watch_target_name, WatchHit, WatchKind, Watchpoint, Watchpoints},
+};
+
+/// How execution continues once the shell is left.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Resume {
+    /// Run until the next breakpoint.
+    Continue,
+    /// Pause again after a single instruction.
+    Step,
+    /// Pause again once execution reaches `addr` with the stack at `depth`, stepping over a `CALL`.
+    Next { addr: usize, depth: usize },
+}
+
+/// Debugging state of the VirtualMachine.
+#[derive(Clone)]
+pub(crate) struct Debugger {
+    pub breakpoints: Breakpoints,
+    pub watchpoints: Watchpoints,
+    pub paused: bool,
+    pub resume: Resume,
+    skip_breakpoint: Option<usize>,
+    watched_pc: Option<usize>,
+    watch_hit: Option<WatchHit>,
+}
+
+impl Debugger {
+    /// Creates a new Debugger with no breakpoints.
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: Breakpoints::new(),
+            watchpoints: Watchpoints::new(),
+            paused: false,
+            resume: Resume::Continue,
+            skip_breakpoint: None,
+            watched_pc: None,
+            watch_hit: None,
+        }
+    }
+
+    /// Lets the next step through a breakpoint at `pc`, as execution has just been moved there.
+    pub fn ignore_breakpoint_at(&mut self, pc: usize) {
+        self.skip_breakpoint = Some(pc);
+    }
+
+    /// Starts checking watchpoints against the accesses made by the instruction at `pc`. Accesses
+    /// made outside of instructions, such as by shell commands, are never reported.
+    pub fn arm(&mut self, pc: usize) {
+        self.watched_pc = Some(pc);
+        self.watch_hit = None;
+    }
+
+    /// Stops checking watchpoints, returning the first watched access made since being armed.
+    pub fn disarm(&mut self) -> Option<WatchHit> {
+        self.watched_pc = None;
+        self.watch_hit.take()
+    }
+
+    /// Checks a read of `val` from a memory address or register against the watchpoints.
+    pub fn check_read(&mut self, addr: u16, val: u16) {
+        self.check_access(addr, WatchKind::Read, val, val);
+    }
+
+    /// Checks a write of `new` over `old` to a memory address or register against the watchpoints.
+    pub fn check_write(&mut self, addr: u16, old: u16, new: u16) {
+        self.check_access(addr, WatchKind::Write, old, new);
+        if old != new {
+            self.check_access(addr, WatchKind::Change, old, new);
+        }
+    }
+
+    /// Checks if execution should stop at a breakpoint before the instruction at `pc`. A
+    /// breakpoint which has just stopped execution will let the following step through.
+    pub fn check_breakpoint(&mut self, pc: usize) -> bool {
+        if self.skip_breakpoint.take() == Some(pc) {
+            return false;
+        }
+
+        if self.breakpoints.hit(pc) {
+            self.skip_breakpoint = Some(pc);
+            return true;
+        }
+        false
+    }
+
+    fn check_access(&mut self, addr: u16, kind: WatchKind, old: u16, new: u16) {
+        let pc = match self.watched_pc {
+            Some(pc) if !self.watchpoints.is_empty() => pc,
+            _ => return,
+        };
+
+        if self.watchpoints.hit(addr, kind) && self.watch_hit.is_none() {
+            self.watch_hit = Some(WatchHit {
+                addr,
+                kind,
+                pc,
+                old,
+                new,
+            });
+        }
+    }
+
+    /// Checks if no breakpoint or watchpoint is set, so that instructions may run without being
+    /// checked against any.
+    pub fn is_idle(&self) -> bool {
+        self.breakpoints.is_empty() && self.watchpoints.is_empty() && self.skip_breakpoint.is_none()
+    }
+
+    /// Checks if execution should pause after an instruction, based on how it was resumed.
+    pub fn should_pause(&self, pc: usize, depth: usize) -> bool {
+        match self.resume {
+            Resume::Continue => false,
+            Resume::Step => true,
+            Resume::Next { addr, depth: d } => pc == addr && depth == d,
+        }
+    }
+}
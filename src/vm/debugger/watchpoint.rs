@@ -0,0 +1,155 @@
+use crate::constants::*;
+use std::{collections::BTreeMap, fmt};
+
+/// The kind of access a watchpoint stops execution on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WatchKind {
+    /// Any read of the value.
+    Read,
+    /// Any write to the value, even if it leaves it unchanged.
+    Write,
+    /// A write which changes the value.
+    Change,
+}
+
+impl WatchKind {
+    /// Parses a WatchKind from its name.
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(WatchKind::Read),
+            "write" => Some(WatchKind::Write),
+            "change" => Some(WatchKind::Change),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchKind::Read => write!(f, "read"),
+            WatchKind::Write => write!(f, "write"),
+            WatchKind::Change => write!(f, "change"),
+        }
+    }
+}
+
+/// A watchpoint on a memory address or register.
+#[derive(Clone, Copy, Debug)]
+pub struct Watchpoint {
+    /// Whether the watchpoint will stop execution.
+    pub enabled: bool,
+    /// The number of times execution has stopped at the watchpoint.
+    pub hits: usize,
+}
+
+/// A watched access made by an instruction.
+#[derive(Clone, Copy, Debug)]
+pub struct WatchHit {
+    /// The accessed memory address, or register reference for addresses from `0x8000` to `0x8007`.
+    pub addr: u16,
+    pub kind: WatchKind,
+    /// The address of the instruction which made the access.
+    pub pc: usize,
+    pub old: u16,
+    pub new: u16,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Watchpoint ({}) on {} hit at {:#06x}: {:#06x} -> {:#06x}",
+            self.kind,
+            watch_target_name(self.addr),
+            self.pc,
+            self.old,
+            self.new
+        )
+    }
+}
+
+/// The set of watchpoints, keyed by memory address or register reference and access kind.
+#[derive(Clone, Default)]
+pub struct Watchpoints {
+    points: BTreeMap<(u16, WatchKind), Watchpoint>,
+}
+
+impl Watchpoints {
+    /// Creates an empty set of watchpoints.
+    pub fn new() -> Self {
+        Watchpoints::default()
+    }
+
+    /// Adds an enabled watchpoint. Returns false if one already exists.
+    pub fn add(&mut self, addr: u16, kind: WatchKind) -> bool {
+        if self.points.contains_key(&(addr, kind)) {
+            return false;
+        }
+
+        self.points.insert(
+            (addr, kind),
+            Watchpoint {
+                enabled: true,
+                hits: 0,
+            },
+        );
+        true
+    }
+
+    /// Removes a watchpoint. Returns false if none exists.
+    pub fn remove(&mut self, addr: u16, kind: WatchKind) -> bool {
+        self.points.remove(&(addr, kind)).is_some()
+    }
+
+    /// Enables a watchpoint. Returns false if none exists.
+    pub fn enable(&mut self, addr: u16, kind: WatchKind) -> bool {
+        self.set_enabled(addr, kind, true)
+    }
+
+    /// Disables a watchpoint without removing it. Returns false if none exists.
+    pub fn disable(&mut self, addr: u16, kind: WatchKind) -> bool {
+        self.set_enabled(addr, kind, false)
+    }
+
+    /// Iterates over all watchpoints in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, WatchKind, &Watchpoint)> {
+        self.points
+            .iter()
+            .map(|(&(addr, kind), wp)| (addr, kind, wp))
+    }
+
+    /// Checks if there are no watchpoints.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Records a hit and returns true if an enabled watchpoint of the given kind exists.
+    pub(crate) fn hit(&mut self, addr: u16, kind: WatchKind) -> bool {
+        match self.points.get_mut(&(addr, kind)) {
+            Some(wp) if wp.enabled => {
+                wp.hits += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn set_enabled(&mut self, addr: u16, kind: WatchKind, enabled: bool) -> bool {
+        match self.points.get_mut(&(addr, kind)) {
+            Some(wp) => {
+                wp.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Formats a watched address as a register name or a hex memory address.
+pub fn watch_target_name(addr: u16) -> String {
+    match REG_NAMES.get(addr.wrapping_sub(FIFTEEN_BIT_MODULO) as usize) {
+        Some(name) => name.to_string(),
+        None => format!("{:#06x}", addr),
+    }
+}
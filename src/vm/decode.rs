@@ -0,0 +1,110 @@
+use crate::{
+    constants::*,
+    vm::op::Op::{self, *},
+};
+use std::ops::Range;
+
+/// The number of addresses in a page of the cache.
+const PAGE_SIZE: usize = 0x100;
+const PAGE_COUNT: usize = MEM_ADDR_SPACE / PAGE_SIZE;
+/// The number of words in the longest instruction, an opcode with three operands.
+const MAX_INSTRUCTION_WORDS: usize = 4;
+
+type Page = [Option<Instruction>; PAGE_SIZE];
+
+/// An operand of a decoded instruction.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Operand {
+    /// A literal value below `0x8000`.
+    Lit(u16),
+    /// A reference to the register with the given index.
+    Reg(u8),
+    /// A value above `0x8007`, which is neither a literal nor a register reference.
+    Invalid(u16),
+}
+
+impl Operand {
+    /// Classifies a raw operand word.
+    pub fn from_u16(n: u16) -> Self {
+        match n.checked_sub(FIFTEEN_BIT_MODULO) {
+            None => Operand::Lit(n),
+            Some(idx) if idx <= 7 => Operand::Reg(idx as u8),
+            Some(_) => Operand::Invalid(n),
+        }
+    }
+
+    /// Gets the raw operand word back, as used for the addresses instructions write to.
+    pub fn to_u16(self) -> u16 {
+        match self {
+            Operand::Lit(n) | Operand::Invalid(n) => n,
+            Operand::Reg(idx) => FIFTEEN_BIT_MODULO + idx as u16,
+        }
+    }
+}
+
+/// An instruction with its opcode and operands decoded.
+#[derive(Clone, Copy)]
+pub(crate) struct Instruction {
+    pub op: Op,
+    pub args: [Operand; 3],
+}
+
+/// Caches decoded instructions by address, so that each one is decoded only once for as long as
+/// the memory it was decoded from is left untouched. Pages of the cache are allocated when the
+/// first instruction in them is decoded.
+pub(crate) struct DecodeCache {
+    pages: Vec<Option<Box<Page>>>,
+}
+
+impl DecodeCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        DecodeCache {
+            pages: (0..PAGE_COUNT).map(|_| None).collect(),
+        }
+    }
+
+    /// Gets the decoded instruction at the given address, if cached.
+    pub fn get(&self, addr: usize) -> Option<Instruction> {
+        match self.pages.get(addr / PAGE_SIZE) {
+            Some(Some(page)) => page[addr % PAGE_SIZE],
+            _ => None,
+        }
+    }
+
+    /// Caches the instruction decoded at the given address. Invalid opcodes are never cached, as
+    /// outside of strict mode a register reference in place of an opcode stands for the opcode
+    /// held by the register.
+    pub fn insert(&mut self, addr: usize, instruction: Instruction) {
+        if let RegOrData(_) = instruction.op {
+            return;
+        }
+        if let Some(page) = self.pages.get_mut(addr / PAGE_SIZE) {
+            page.get_or_insert_with(|| Box::new([None; PAGE_SIZE]))[addr % PAGE_SIZE] =
+                Some(instruction);
+        }
+    }
+
+    /// Drops every cached instruction which overlaps the given address.
+    pub fn invalidate(&mut self, addr: usize) {
+        self.invalidate_range(addr..addr + 1);
+    }
+
+    /// Drops every cached instruction which overlaps the given range of addresses.
+    pub fn invalidate_range(&mut self, addrs: Range<usize>) {
+        let start = addrs.start.saturating_sub(MAX_INSTRUCTION_WORDS - 1);
+
+        for addr in start..addrs.end {
+            if let Some(Some(page)) = self.pages.get_mut(addr / PAGE_SIZE) {
+                page[addr % PAGE_SIZE] = None;
+            }
+        }
+    }
+
+    /// Drops every cached instruction.
+    pub fn clear(&mut self) {
+        for page in self.pages.iter_mut() {
+            *page = None;
+        }
+    }
+}
@@ -0,0 +1,680 @@
+use crate::constants::*;
+
+/// The type of a command argument, which decides how it is parsed, shown in usage and completed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ArgKind {
+    /// A fixed word selecting a form of the command, such as `on` or `off`.
+    Keyword,
+    /// An option which may be given anywhere after the command name, such as `--coverage`.
+    Flag,
+    /// A register name, from `AX` to `HX`.
+    Register,
+    /// A memory address, in decimal or in hex with a `0x` prefix.
+    Address,
+    /// A number, in decimal, in hex with a `0x` prefix, or as a character literal such as `'a'`.
+    Value,
+    /// A word to store in memory, given as a value or as a register name standing for its
+    /// reference.
+    Word,
+    /// A number of items, in decimal or in hex with a `0x` prefix.
+    Count,
+    /// A file path.
+    Path,
+    /// Any word.
+    Text,
+    /// One of the given words.
+    Choice(&'static [&'static str]),
+    /// A memory address or a register name.
+    Target,
+}
+
+/// An argument in one of the forms a command accepts.
+#[derive(Clone, Copy)]
+pub struct Arg {
+    /// The name shown in usage, or the word itself for keywords and flags.
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+    pub repeated: bool,
+}
+
+impl Arg {
+    fn new(name: &'static str, kind: ArgKind) -> Self {
+        Arg {
+            name,
+            kind,
+            optional: false,
+            repeated: false,
+        }
+    }
+
+    pub fn keyword(word: &'static str) -> Self {
+        Arg::new(word, ArgKind::Keyword)
+    }
+
+    pub fn flag(flag: &'static str) -> Self {
+        Arg {
+            optional: true,
+            ..Arg::new(flag, ArgKind::Flag)
+        }
+    }
+
+    pub fn register(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Register)
+    }
+
+    pub fn address(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Address)
+    }
+
+    pub fn value(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Value)
+    }
+
+    pub fn word(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Word)
+    }
+
+    pub fn count(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Count)
+    }
+
+    pub fn path(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Path)
+    }
+
+    pub fn text(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Text)
+    }
+
+    pub fn choice(name: &'static str, choices: &'static [&'static str]) -> Self {
+        Arg::new(name, ArgKind::Choice(choices))
+    }
+
+    pub fn target(name: &'static str) -> Self {
+        Arg::new(name, ArgKind::Target)
+    }
+
+    /// Makes the argument optional. Only the last positional arguments of a form may be optional.
+    pub fn optional(self) -> Self {
+        Arg {
+            optional: true,
+            ..self
+        }
+    }
+
+    /// Makes the argument take every remaining word. Only the last argument of a form may repeat.
+    pub fn repeated(self) -> Self {
+        Arg {
+            repeated: true,
+            ..self
+        }
+    }
+
+    /// Formats the argument as shown in usage, e.g. `<address>`, `[count]` or `<value...>`.
+    pub fn usage(&self) -> String {
+        let name = match self.kind {
+            ArgKind::Keyword => return self.name.into(),
+            ArgKind::Flag => return format!("[{}]", self.name),
+            ArgKind::Choice(choices) => choices.join("|"),
+            _ => self.name.into(),
+        };
+        let dots = if self.repeated { "..." } else { "" };
+
+        if self.optional {
+            format!("[{}{}]", name, dots)
+        } else {
+            format!("<{}{}>", name, dots)
+        }
+    }
+
+    /// Parses a word given for the argument.
+    fn parse(&self, s: &str) -> Option<ArgValue> {
+        match self.kind {
+            ArgKind::Keyword | ArgKind::Flag => None,
+            ArgKind::Register => reg_idx_from_str(s).map(ArgValue::Register),
+            ArgKind::Address => parse_addr(s).map(ArgValue::Address),
+            ArgKind::Value => parse_value(s).map(ArgValue::Value),
+            ArgKind::Word => reg_idx_from_str(s)
+                .or_else(|| parse_value(s))
+                .map(ArgValue::Value),
+            ArgKind::Count => parse_count(s).map(ArgValue::Count),
+            ArgKind::Path => Some(ArgValue::Path(s.into())),
+            ArgKind::Text => Some(ArgValue::Text),
+            ArgKind::Choice(choices) => choices
+                .iter()
+                .find(|&&x| x == s)
+                .map(|&x| ArgValue::Choice(x)),
+            ArgKind::Target => reg_idx_from_str(s)
+                .or_else(|| parse_addr(s).map(|x| x as u16))
+                .map(ArgValue::Target),
+        }
+    }
+
+    /// Lists the values the argument may take, where there are only a few.
+    fn possible_values(&self) -> Option<String> {
+        match self.kind {
+            ArgKind::Register => Some(REG_NAMES.join(", ")),
+            ArgKind::Choice(choices) => Some(choices.join(", ")),
+            _ => None,
+        }
+    }
+}
+
+/// Explains how the numbers taken by the given forms may be written, for the notes printed below
+/// the usage of a command.
+pub fn notation(forms: &[Vec<Arg>]) -> Option<String> {
+    let args = forms.iter().flatten().collect::<Vec<_>>();
+    let names_of = |kinds: &[ArgKind]| {
+        let mut names = vec![];
+        for arg in args.iter().filter(|x| kinds.contains(&x.kind)) {
+            let name = plural(arg.name);
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    };
+    let numbers = names_of(&[
+        ArgKind::Address,
+        ArgKind::Count,
+        ArgKind::Value,
+        ArgKind::Word,
+    ]);
+    let values = names_of(&[ArgKind::Value, ArgKind::Word]);
+    let mut sentences = vec![];
+
+    if !numbers.is_empty() {
+        let mut sentence = format!(
+            "{} may be given in decimal, or in hex with a `0x` prefix",
+            capitalize(&join(&numbers))
+        );
+        if !values.is_empty() {
+            sentence += &format!(
+                ", and {} also as a character literal such as `'a'`",
+                join(&values)
+            );
+        }
+        if args.iter().any(|x| x.kind == ArgKind::Word) {
+            sentence += " or as a register name";
+        }
+        sentences.push(sentence + ".");
+    }
+    if args.iter().any(|x| x.kind == ArgKind::Target) {
+        sentences.push(
+            "Targets are register names, or addresses in decimal or in hex with a `0x` prefix."
+                .into(),
+        );
+    }
+
+    if sentences.is_empty() {
+        None
+    } else {
+        Some(wrap(&sentences.join(" ")))
+    }
+}
+
+/// Breaks text into lines of up to 100 characters, as the notes of commands are written.
+fn wrap(text: &str) -> String {
+    let mut wrapped = String::new();
+    let mut line_len = 0;
+
+    for word in text.split(' ') {
+        if line_len > 0 && line_len + 1 + word.len() > 100 {
+            wrapped.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            wrapped.push(' ');
+            line_len += 1;
+        }
+        wrapped += word;
+        line_len += word.len();
+    }
+    wrapped
+}
+
+/// Makes the name of an argument plural, e.g. `addresses` from `address`.
+fn plural(name: &str) -> String {
+    if name.ends_with('s') {
+        format!("{}es", name)
+    } else {
+        format!("{}s", name)
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Joins names into a list such as `a, b and c`.
+fn join(names: &[String]) -> String {
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// A parsed argument.
+#[derive(Clone)]
+pub enum ArgValue {
+    /// The reference of a register, from `0x8000` to `0x8007`.
+    Register(u16),
+    Address(usize),
+    Value(u16),
+    Count(usize),
+    Path(String),
+    /// A word of free text, which commands take as typed from `ArgValues::raw`.
+    Text,
+    Choice(&'static str),
+    /// A memory address, or the reference of a register.
+    Target(u16),
+}
+
+/// The arguments given to a command, parsed according to the form they matched.
+#[derive(Default)]
+pub struct ArgValues {
+    keywords: Vec<&'static str>,
+    flags: Vec<&'static str>,
+    values: Vec<(&'static str, ArgValue)>,
+    raw: String,
+}
+
+impl ArgValues {
+    /// Keeps the text given after the command name, exactly as typed.
+    pub(crate) fn with_raw(self, raw: String) -> Self {
+        ArgValues { raw, ..self }
+    }
+
+    /// Gets the text given after the command name, exactly as typed, for commands which take the
+    /// rest of the line as it is.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Gets the first keyword of the matched form, which names its subcommand.
+    pub fn subcommand(&self) -> Option<&'static str> {
+        self.keywords.first().copied()
+    }
+
+    /// Checks if the given flag was given.
+    pub fn flag(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    fn get(&self, name: &str) -> Vec<&ArgValue> {
+        self.values
+            .iter()
+            .filter(|(x, _)| *x == name)
+            .map(|(_, val)| val)
+            .collect()
+    }
+
+    pub fn register(&self, name: &str) -> Option<u16> {
+        self.get(name).into_iter().find_map(|x| match x {
+            ArgValue::Register(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    pub fn address(&self, name: &str) -> Option<usize> {
+        self.get(name).into_iter().find_map(|x| match x {
+            ArgValue::Address(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    pub fn value(&self, name: &str) -> Option<u16> {
+        self.values(name).first().copied()
+    }
+
+    /// Gets every value given for a repeated argument.
+    pub fn values(&self, name: &str) -> Vec<u16> {
+        self.get(name)
+            .into_iter()
+            .filter_map(|x| match x {
+                ArgValue::Value(x) => Some(*x),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn count(&self, name: &str) -> Option<usize> {
+        self.get(name).into_iter().find_map(|x| match x {
+            ArgValue::Count(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    pub fn path(&self, name: &str) -> Option<&str> {
+        self.get(name).into_iter().find_map(|x| match x {
+            ArgValue::Path(x) => Some(x.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn choice(&self, name: &str) -> Option<&'static str> {
+        self.get(name).into_iter().find_map(|x| match x {
+            ArgValue::Choice(x) => Some(*x),
+            _ => None,
+        })
+    }
+
+    pub fn target(&self, name: &str) -> Option<u16> {
+        self.get(name).into_iter().find_map(|x| match x {
+            ArgValue::Target(x) => Some(*x),
+            _ => None,
+        })
+    }
+}
+
+/// Why the arguments given do not match a form.
+enum ArgError {
+    TooMany,
+    UnknownOption(String),
+    Missing,
+    Invalid(Arg, String),
+}
+
+impl ArgError {
+    /// Ranks errors from the least to the most specific, so that the most telling one is shown.
+    fn severity(&self) -> usize {
+        match self {
+            ArgError::TooMany => 0,
+            ArgError::UnknownOption(_) => 1,
+            ArgError::Missing => 2,
+            ArgError::Invalid(_, _) => 3,
+        }
+    }
+}
+
+/// Parses the arguments given to a command against each of its forms in order, returning the
+/// values of the first form they match. Otherwise the message explaining why they fail to match
+/// the form they come closest to is returned.
+pub fn parse_args(forms: &[Vec<Arg>], args: &[String]) -> Result<ArgValues, String> {
+    let mut closest: Option<(usize, ArgError)> = None;
+
+    for form in forms {
+        match match_form(form, args) {
+            Ok(values) => return Ok(values),
+            Err((matched, err)) => {
+                let better = closest.as_ref().is_none_or(|(x, closest)| {
+                    (matched, err.severity()) > (*x, closest.severity())
+                });
+                if better {
+                    closest = Some((matched, err));
+                }
+            }
+        }
+    }
+
+    Err(match closest.map(|(_, err)| err) {
+        Some(ArgError::TooMany) | None => "Too many arguments given.".into(),
+        Some(ArgError::UnknownOption(x)) => format!("No option \"{}\" exists", x),
+        Some(ArgError::Missing) => "Not enough arguments given.".into(),
+        Some(ArgError::Invalid(arg, s)) => match arg.possible_values() {
+            Some(values) => format!(
+                "Invalid {} given: {}\nPossible values: {}",
+                arg.name, s, values
+            ),
+            None => format!("Invalid {} given: {}", arg.name, s),
+        },
+    })
+}
+
+/// Matches the arguments against a form. Errors come with the number of keywords matched before
+/// failing, which tells the form the arguments were meant for.
+fn match_form(form: &[Arg], args: &[String]) -> Result<ArgValues, (usize, ArgError)> {
+    let (flags, words) = split_flags(form, args);
+    let mut values = ArgValues {
+        flags,
+        ..Default::default()
+    };
+    let mut words = words.into_iter().peekable();
+
+    for arg in form.iter().filter(|x| x.kind != ArgKind::Flag) {
+        let matched = values.keywords.len();
+
+        if arg.kind == ArgKind::Keyword {
+            match words.next() {
+                Some(word) if word == arg.name => values.keywords.push(arg.name),
+                Some(word) => return Err((matched, ArgError::UnknownOption(word.into()))),
+                None => return Err((matched, ArgError::Missing)),
+            }
+            continue;
+        }
+
+        let mut count = 0;
+        while let Some(word) = words.next_if(|_| count == 0 || arg.repeated) {
+            match arg.parse(word) {
+                Some(val) => values.values.push((arg.name, val)),
+                None => return Err((matched, ArgError::Invalid(*arg, word.into()))),
+            }
+            count += 1;
+        }
+        if count == 0 && !arg.optional {
+            return Err((matched, ArgError::Missing));
+        }
+    }
+
+    match words.next() {
+        Some(word) if word.starts_with("--") => {
+            Err((values.keywords.len(), ArgError::UnknownOption(word.into())))
+        }
+        Some(_) => Err((values.keywords.len(), ArgError::TooMany)),
+        None => Ok(values),
+    }
+}
+
+/// Splits the words given into the flags of the form and the positional words.
+fn split_flags<'a>(form: &[Arg], args: &'a [String]) -> (Vec<&'static str>, Vec<&'a str>) {
+    let mut flags = vec![];
+    let mut words = vec![];
+
+    for arg in args {
+        match form
+            .iter()
+            .find(|x| x.kind == ArgKind::Flag && x.name == arg)
+        {
+            Some(flag) => flags.push(flag.name),
+            None => words.push(arg.as_str()),
+        }
+    }
+    (flags, words)
+}
+
+/// Gets the arguments which may follow the words typed so far, in any of the forms.
+pub fn next_args(forms: &[Vec<Arg>], typed: &[&str]) -> Vec<Arg> {
+    let mut next = vec![];
+
+    'forms: for form in forms {
+        let is_flag = |word: &str| {
+            form.iter()
+                .any(|x| x.kind == ArgKind::Flag && x.name == word)
+        };
+        let mut positional = form.iter().filter(|x| x.kind != ArgKind::Flag);
+        let mut current = positional.next();
+
+        for &word in typed.iter().filter(|&&x| !is_flag(x)) {
+            match current {
+                None => continue 'forms,
+                Some(arg) if arg.kind == ArgKind::Keyword && arg.name != word => continue 'forms,
+                Some(arg) if arg.repeated => {}
+                Some(_) => current = positional.next(),
+            }
+        }
+
+        next.extend(current.copied());
+        next.extend(
+            form.iter()
+                .filter(|x| x.kind == ArgKind::Flag && !typed.contains(&x.name)),
+        );
+    }
+    next
+}
+
+pub fn reg_idx_from_str(s: &str) -> Option<u16> {
+    REG_NAMES.iter().enumerate().fold(None, |acc, (i, name)| {
+        if &s == name {
+            Some(i as u16 + 0x8000)
+        } else {
+            acc
+        }
+    })
+}
+
+/// Parses a memory address given in decimal, or in hex with a `0x` prefix.
+pub fn parse_addr(s: &str) -> Option<usize> {
+    parse_count(s).filter(|&addr| addr < MEM_ADDR_SPACE)
+}
+
+/// Parses a number given in decimal, or in hex with a `0x` prefix.
+fn parse_count(s: &str) -> Option<usize> {
+    match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Parses a value given in decimal, in hex with a `0x` prefix, or as a character literal.
+pub fn parse_value(s: &str) -> Option<u16> {
+    let mut chars = s.chars();
+    if let (Some('\''), Some(c), Some('\''), None) =
+        (chars.next(), chars.next(), chars.next(), chars.next())
+    {
+        return if c.is_ascii() { Some(c as u16) } else { None };
+    }
+
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    /// The forms of a command like `breakpoint`.
+    fn forms() -> Vec<Vec<Arg>> {
+        vec![
+            vec![Arg::keyword("add"), Arg::address("address").repeated()],
+            vec![Arg::keyword("list"), Arg::flag("--all")],
+            vec![
+                Arg::keyword("set"),
+                Arg::register("register"),
+                Arg::value("value"),
+            ],
+            vec![
+                Arg::keyword("watch"),
+                Arg::choice("kind", &["read", "write"]),
+            ],
+        ]
+    }
+
+    #[test]
+    fn values_in_every_notation() {
+        assert_eq!(parse_value("42"), Some(42));
+        assert_eq!(parse_value("0x2a"), Some(42));
+        assert_eq!(parse_value("'*'"), Some(42));
+        assert_eq!(parse_value("BX"), None);
+        assert_eq!(parse_value("0x10000"), None);
+        assert_eq!(parse_value("'ab'"), None);
+        assert_eq!(parse_value("forty"), None);
+    }
+
+    #[test]
+    fn only_words_may_be_register_names() {
+        let word = Arg::word("value");
+        assert!(matches!(word.parse("BX"), Some(ArgValue::Value(0x8001))));
+        assert!(matches!(word.parse("'*'"), Some(ArgValue::Value(42))));
+        assert!(Arg::value("value").parse("BX").is_none());
+    }
+
+    #[test]
+    fn addresses_must_be_in_memory() {
+        assert_eq!(parse_addr("0x7fff"), Some(0x7fff));
+        assert_eq!(parse_addr("32768"), None);
+        assert_eq!(reg_idx_from_str("HX"), Some(0x8007));
+        assert_eq!(reg_idx_from_str("IX"), None);
+    }
+
+    #[test]
+    fn matches_the_form_named_by_its_keyword() {
+        let values = parse_args(&forms(), &words("add 0x10 20")).ok().unwrap();
+        assert_eq!(values.subcommand(), Some("add"));
+        assert_eq!(values.values.len(), 2);
+        assert_eq!(values.address("address"), Some(0x10));
+
+        let values = parse_args(&forms(), &words("list --all")).ok().unwrap();
+        assert_eq!(values.subcommand(), Some("list"));
+        assert!(values.flag("--all"));
+
+        let values = parse_args(&forms(), &words("set CX 'a'")).ok().unwrap();
+        assert_eq!(values.register("register"), Some(0x8002));
+        assert_eq!(values.value("value"), Some(97));
+
+        let values = parse_args(&forms(), &words("watch write")).ok().unwrap();
+        assert_eq!(values.choice("kind"), Some("write"));
+    }
+
+    #[test]
+    fn explains_the_closest_mismatch() {
+        let err = |s: &str| parse_args(&forms(), &words(s)).err().unwrap();
+
+        assert_eq!(err("add"), "Not enough arguments given.");
+        assert_eq!(err("list extra"), "Too many arguments given.");
+        assert_eq!(err("list --none"), "No option \"--none\" exists");
+        assert_eq!(err("remove 1"), "No option \"remove\" exists");
+        assert_eq!(err("add 0x8000"), "Invalid address given: 0x8000");
+        assert_eq!(
+            err("set IX 1"),
+            "Invalid register given: IX\nPossible values: AX, BX, CX, DX, EX, FX, GX, HX"
+        );
+        assert_eq!(
+            err("watch exec"),
+            "Invalid kind given: exec\nPossible values: read, write"
+        );
+    }
+
+    #[test]
+    fn explains_the_notation_of_numbers() {
+        assert_eq!(
+            notation(&forms()).unwrap(),
+            "Addresses and values may be given in decimal, or in hex with a `0x` prefix, and values also as a
+character literal such as `'a'`."
+        );
+        assert_eq!(
+            notation(&[vec![Arg::word("value"), Arg::target("target")]]).unwrap(),
+            "Values may be given in decimal, or in hex with a `0x` prefix, and values also as a character literal
+such as `'a'` or as a register name. Targets are register names, or addresses in decimal or in hex
+with a `0x` prefix."
+        );
+        assert!(notation(&[vec![Arg::keyword("list")]]).is_none());
+    }
+
+    #[test]
+    fn suggests_the_arguments_which_may_follow() {
+        let names = |typed: &[&str]| {
+            next_args(&forms(), typed)
+                .iter()
+                .map(|x| x.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&[]), vec!["add", "list", "--all", "set", "watch"]);
+        assert_eq!(names(&["list"]), vec!["--all"]);
+        assert_eq!(names(&["set"]), vec!["register"]);
+        assert_eq!(names(&["set", "AX"]), vec!["value"]);
+        assert_eq!(names(&["add", "1", "2"]), vec!["address"]);
+        assert!(names(&["set", "AX", "1"]).is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct BreakpointCommand;
+
+impl CommandExecutor for BreakpointCommand {
+    fn name(&self) -> String {
+        "break".into()
+    }
+
+    fn descr(&self) -> String {
+        "Set, list, enable or disable breakpoints".into()
+    }
+
+    fn title(&self) -> String {
+        "Manage Breakpoints".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![],
+            vec![Arg::address("address")],
+            vec![Arg::keyword("--enable"), Arg::address("address")],
+            vec![Arg::keyword("--disable"), Arg::address("address")],
+        ]
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let addr = match args.address("address") {
+            Some(x) => x,
+            None => {
+                if vm.breakpoints().is_empty() {
+                    println!("No breakpoints set.");
+                }
+                for (addr, bp) in vm.breakpoints().iter() {
+                    println!(
+                        "{:#06x}\t{}\thits: {}\t{}",
+                        addr,
+                        if bp.enabled { "enabled" } else { "disabled" },
+                        bp.hits,
+                        vm.disassemble_instruction(addr)
+                    );
+                }
+                return Ok(());
+            }
+        };
+
+        let flag = args.subcommand();
+        let updated = match flag {
+            Some("--enable") => vm.breakpoints_mut().enable(addr),
+            Some("--disable") => vm.breakpoints_mut().disable(addr),
+            _ => vm.breakpoints_mut().add(addr),
+        };
+
+        match (flag, updated) {
+            (None, true) => println!("Breakpoint set at {:#06x}", addr),
+            (None, false) => println!("Breakpoint already set at {:#06x}", addr),
+            (_, true) => println!("Breakpoint at {:#06x} updated", addr),
+            (_, false) => println!("No breakpoint set at {:#06x}", addr),
+        }
+        Ok(())
+    }
+}
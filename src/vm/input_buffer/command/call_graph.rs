@@ -0,0 +1,64 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct CallGraphCommand;
+
+impl CommandExecutor for CallGraphCommand {
+    fn name(&self) -> String {
+        "callgraph".into()
+    }
+
+    fn descr(&self) -> String {
+        "Records the call graph of executed subroutines".into()
+    }
+
+    fn title(&self) -> String {
+        "Record Call Graph".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![],
+            vec![Arg::keyword("on")],
+            vec![Arg::keyword("off")],
+            vec![Arg::keyword("export"), Arg::path("out_file")],
+        ]
+    }
+
+    fn notes(&self) -> String {
+        "Graphs are exported as JSON if the out_file has a `.json` extension, and as Graphviz DOT
+otherwise."
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        match args.subcommand() {
+            Some("on") => {
+                vm.start_call_graph();
+                println!("Call graph recording started");
+            }
+
+            Some("off") => {
+                vm.stop_call_graph();
+                println!("Call graph recording stopped");
+            }
+
+            Some(_) => match vm.call_graph() {
+                Some(graph) => graph.write_to_file(args.path("out_file").unwrap())?,
+                None => println!("No call graph is being recorded."),
+            },
+
+            None => match vm.call_graph() {
+                Some(graph) => println!(
+                    "Recording call graph: {} edges so far.",
+                    graph.edges().count()
+                ),
+                None => println!("No call graph is being recorded."),
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{
+    vm::debugger::Resume,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct ContinueCommand;
+
+impl CommandExecutor for ContinueCommand {
+    fn name(&self) -> String {
+        "continue".into()
+    }
+
+    fn descr(&self) -> String {
+        "Resumes execution until the next breakpoint".into()
+    }
+
+    fn title(&self) -> String {
+        "Continue Execution".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
+    }
+
+    fn notes(&self) -> String {
+        "Resumes execution until the next breakpoint.".into()
+    }
+
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        if !vm.resume(Resume::Continue) {
+            println!("The VM is not paused.");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct CoverageCommand;
+
+impl CommandExecutor for CoverageCommand {
+    fn name(&self) -> String {
+        "coverage".into()
+    }
+
+    fn descr(&self) -> String {
+        "Records which addresses are executed, read and written".into()
+    }
+
+    fn title(&self) -> String {
+        "Record Code Coverage".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![], vec![Arg::keyword("on")], vec![Arg::keyword("off")]]
+    }
+
+    fn notes(&self) -> String {
+        "Use `disassemble <outfile> --coverage` to see the covered instructions. Instructions are marked
+`+` if executed and `-` if never executed, while data is marked `r` if read and `w` if written."
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        match args.subcommand() {
+            Some("on") => {
+                vm.start_coverage();
+                println!("Coverage recording started");
+            }
+
+            Some(_) => {
+                vm.stop_coverage();
+                println!("Coverage recording stopped");
+            }
+
+            None => match vm.coverage() {
+                Some(coverage) => println!(
+                    "Executed: {} addresses, read: {}, written: {}",
+                    coverage.executed_count(),
+                    coverage.read_count(),
+                    coverage.written_count()
+                ),
+                None => println!("Coverage is not being recorded."),
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct DeleteBreakpointCommand;
+
+impl CommandExecutor for DeleteBreakpointCommand {
+    fn name(&self) -> String {
+        "delete".into()
+    }
+
+    fn descr(&self) -> String {
+        "Delete a breakpoint".into()
+    }
+
+    fn title(&self) -> String {
+        "Delete Breakpoint".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::address("address")]]
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let addr = args.address("address").unwrap();
+
+        if vm.breakpoints_mut().remove(addr) {
+            println!("Breakpoint at {:#06x} deleted", addr);
+        } else {
+            println!("No breakpoint set at {:#06x}", addr);
+        }
+        Ok(())
+    }
+}
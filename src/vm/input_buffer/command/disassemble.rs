@@ -1,5 +1,5 @@
 use crate::{
-    vm::input_buffer::command::{Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     Result, VirtualMachine,
 };
 use std::fs;
@@ -16,32 +16,28 @@ impl CommandExecutor for DisassembleCommand {
         "Disassembles the vm memory to a file".into()
     }
 
-    fn usage(&self, with_header: bool) -> String {
-        format!(
-            "{}\
-Usage:
-    disassemble --help
-    disassemble <outfile> [options]
-
-Options:
-    --with-addresses  Specify that the assembly should be addressed\
-    ",
-            if with_header {
-                "Disassemble Program Memory\n\n"
-            } else {
-                ""
-            }
-        )
+    fn title(&self) -> String {
+        "Disassemble Program Memory".into()
     }
 
-    fn required_args(&self) -> usize {
-        1
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![
+            Arg::path("outfile"),
+            Arg::flag("--with-addresses"),
+            Arg::flag("--coverage"),
+        ]]
     }
 
-    fn exec(&self, args: Args, vm: &mut VirtualMachine) -> Result<()> {
-        let with_addresses = args.len() > 1 && args[1] == "--with-addresses";
-        let out_path = &args[0];
-        let asm = vm.disassemble(with_addresses);
+    fn notes(&self) -> String {
+        "Options:
+    --with-addresses  Specify that the assembly should be addressed
+    --coverage        Mark executed and never-executed instructions, see `coverage`"
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let out_path = args.path("outfile").unwrap();
+        let asm = vm.disassemble(args.flag("--with-addresses"), args.flag("--coverage"));
 
         fs::write(out_path, asm)?;
         Ok(())
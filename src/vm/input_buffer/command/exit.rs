@@ -1,8 +1,7 @@
 use crate::{
-    vm::input_buffer::command::{Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     Result, VirtualMachine,
 };
-use std::process;
 
 #[derive(Clone, Copy)]
 pub struct ExitCommand;
@@ -16,15 +15,20 @@ impl CommandExecutor for ExitCommand {
         "Exits the virtual machine runtime".into()
     }
 
-    fn usage(&self, _: bool) -> String {
-        "Exits the virtual machine runtime".into()
+    fn title(&self) -> String {
+        "Exit".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
     }
 
-    fn required_args(&self) -> usize {
-        0
+    fn notes(&self) -> String {
+        "Exits the virtual machine runtime.".into()
     }
 
-    fn exec(&self, _: Args, _: &mut VirtualMachine) -> Result<()> {
-        process::exit(0)
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        vm.request_exit();
+        Ok(())
     }
 }
@@ -0,0 +1,53 @@
+use crate::{
+    constants::*,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct FillCommand;
+
+impl CommandExecutor for FillCommand {
+    fn name(&self) -> String {
+        "fill".into()
+    }
+
+    fn descr(&self) -> String {
+        "Fill a range of memory with a value".into()
+    }
+
+    fn title(&self) -> String {
+        "Fill Memory".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![
+            Arg::address("address"),
+            Arg::count("length"),
+            Arg::word("value"),
+        ]]
+    }
+
+    fn notes(&self) -> String {
+        "Writes <value> to <length> words of memory starting at <address>.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let addr = args.address("address").unwrap();
+        let len = args.count("length").unwrap();
+        let val = args.value("value").unwrap();
+
+        if len > MEM_ADDR_SPACE - addr {
+            return vm.print_line(&format!(
+                "Filling {} words at {:#06x} runs past the end of memory",
+                len, addr
+            ));
+        }
+
+        vm.edit(|vm| (addr..addr + len).try_for_each(|a| vm.poke_mem(a, val)))?;
+        vm.print_line(&format!(
+            "Filled {} words at {:#06x} with {:#06x}",
+            len, addr, val
+        ))
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     error::Error,
-    vm::input_buffer::command::{Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     vm::VirtualMachineState,
     Result, VirtualMachine,
 };
@@ -18,27 +18,16 @@ impl CommandExecutor for LoadCommand {
         "Loads the vm state from a file".into()
     }
 
-    fn usage(&self, with_header: bool) -> String {
-        format!(
-            "{}\
-Usage:
-    load --help
-    load <state_file>\
-    ",
-            if with_header {
-                "Loads Program State\n\n"
-            } else {
-                ""
-            }
-        )
+    fn title(&self) -> String {
+        "Loads Program State".into()
     }
 
-    fn required_args(&self) -> usize {
-        1
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::path("state_file")]]
     }
 
-    fn exec(&self, args: Args, vm: &mut VirtualMachine) -> Result<()> {
-        let state_str = fs::read_to_string(&args[0])?;
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let state_str = fs::read_to_string(args.path("state_file").unwrap())?;
         let state = ron::from_str::<VirtualMachineState>(&state_str).map_err(|e| {
             Error::DeserializeErr {
                 pc: vm.pc(),
@@ -1,21 +1,77 @@
+mod args;
+mod breakpoint;
+mod call_graph;
+mod continue_exec;
+mod coverage;
+mod delete_breakpoint;
 mod disassemble;
 mod exit;
+mod fill;
 mod load;
+mod next;
+mod peek;
+mod poke;
+mod pop;
 mod print_register;
 mod print_stack;
+mod profile;
+mod push;
+mod reverse;
+mod reverse_continue;
 mod save;
+mod scan;
+mod self_modify;
+mod send;
+mod set_pc;
 mod set_register;
+mod step;
+mod step_back;
+mod trace;
+mod transcript;
+mod unwatch;
 mod vmhelp;
+mod watch;
 
 pub use self::{
-    disassemble::DisassembleCommand, exit::ExitCommand, load::LoadCommand,
-    print_register::PrintRegisterCommand, print_stack::PrintStackCommand, save::SaveCommand,
-    set_register::SetRegisterCommand, vmhelp::VmHelpCommand,
+    args::{Arg, ArgKind, ArgValues},
+    breakpoint::BreakpointCommand,
+    call_graph::CallGraphCommand,
+    continue_exec::ContinueCommand,
+    coverage::CoverageCommand,
+    delete_breakpoint::DeleteBreakpointCommand,
+    disassemble::DisassembleCommand,
+    exit::ExitCommand,
+    fill::FillCommand,
+    load::LoadCommand,
+    next::NextCommand,
+    peek::PeekCommand,
+    poke::PokeCommand,
+    pop::PopCommand,
+    print_register::PrintRegisterCommand,
+    print_stack::PrintStackCommand,
+    profile::ProfileCommand,
+    push::PushCommand,
+    reverse::ReverseCommand,
+    reverse_continue::ReverseContinueCommand,
+    save::SaveCommand,
+    scan::ScanCommand,
+    self_modify::SelfModifyCommand,
+    send::SendCommand,
+    set_pc::SetPcCommand,
+    set_register::SetRegisterCommand,
+    step::StepCommand,
+    step_back::StepBackCommand,
+    trace::TraceCommand,
+    transcript::TranscriptCommand,
+    unwatch::UnwatchCommand,
+    vmhelp::VmHelpCommand,
+    watch::WatchCommand,
 };
 
-use crate::{constants::*, Result, VirtualMachine};
+use self::args::{next_args, notation, parse_args};
+use crate::{Result, VirtualMachine};
 
-const COMMAND_NAMES: [&str; 8] = [
+const COMMAND_NAMES: [&str; 32] = [
     "vmhelp",
     "exit",
     "save",
@@ -24,9 +80,38 @@ const COMMAND_NAMES: [&str; 8] = [
     "setreg",
     "printreg",
     "printstack",
+    "break",
+    "delete",
+    "continue",
+    "step",
+    "next",
+    "watch",
+    "unwatch",
+    "trace",
+    "reverse",
+    "stepback",
+    "rcontinue",
+    "profile",
+    "callgraph",
+    "coverage",
+    "selfmod",
+    "transcript",
+    "peek",
+    "poke",
+    "fill",
+    "setpc",
+    "push",
+    "pop",
+    "scan",
+    "send",
 ];
 
-pub type Args = Vec<String>;
+/// The words given after the name of a command, along with the rest of the line they were split
+/// from, exactly as typed.
+pub struct Args {
+    pub words: Vec<String>,
+    pub raw: String,
+}
 
 pub struct Command {
     cmd: Box<dyn CommandExecutor>,
@@ -59,10 +144,86 @@ impl Command {
             x if x == PrintStackCommand.name() => Some(Command {
                 cmd: Box::new(PrintStackCommand),
             }),
+            x if x == BreakpointCommand.name() => Some(Command {
+                cmd: Box::new(BreakpointCommand),
+            }),
+            x if x == DeleteBreakpointCommand.name() => Some(Command {
+                cmd: Box::new(DeleteBreakpointCommand),
+            }),
+            x if x == ContinueCommand.name() => Some(Command {
+                cmd: Box::new(ContinueCommand),
+            }),
+            x if x == StepCommand.name() => Some(Command {
+                cmd: Box::new(StepCommand),
+            }),
+            x if x == NextCommand.name() => Some(Command {
+                cmd: Box::new(NextCommand),
+            }),
+            x if x == WatchCommand.name() => Some(Command {
+                cmd: Box::new(WatchCommand),
+            }),
+            x if x == UnwatchCommand.name() => Some(Command {
+                cmd: Box::new(UnwatchCommand),
+            }),
+            x if x == TraceCommand.name() => Some(Command {
+                cmd: Box::new(TraceCommand),
+            }),
+            x if x == ReverseCommand.name() => Some(Command {
+                cmd: Box::new(ReverseCommand),
+            }),
+            x if x == StepBackCommand.name() => Some(Command {
+                cmd: Box::new(StepBackCommand),
+            }),
+            x if x == ReverseContinueCommand.name() => Some(Command {
+                cmd: Box::new(ReverseContinueCommand),
+            }),
+            x if x == ProfileCommand.name() => Some(Command {
+                cmd: Box::new(ProfileCommand),
+            }),
+            x if x == CallGraphCommand.name() => Some(Command {
+                cmd: Box::new(CallGraphCommand),
+            }),
+            x if x == CoverageCommand.name() => Some(Command {
+                cmd: Box::new(CoverageCommand),
+            }),
+            x if x == SelfModifyCommand.name() => Some(Command {
+                cmd: Box::new(SelfModifyCommand),
+            }),
+            x if x == TranscriptCommand.name() => Some(Command {
+                cmd: Box::new(TranscriptCommand),
+            }),
+            x if x == PeekCommand.name() => Some(Command {
+                cmd: Box::new(PeekCommand),
+            }),
+            x if x == PokeCommand.name() => Some(Command {
+                cmd: Box::new(PokeCommand),
+            }),
+            x if x == FillCommand.name() => Some(Command {
+                cmd: Box::new(FillCommand),
+            }),
+            x if x == SetPcCommand.name() => Some(Command {
+                cmd: Box::new(SetPcCommand),
+            }),
+            x if x == PushCommand.name() => Some(Command {
+                cmd: Box::new(PushCommand),
+            }),
+            x if x == PopCommand.name() => Some(Command {
+                cmd: Box::new(PopCommand),
+            }),
+            x if x == ScanCommand.name() => Some(Command {
+                cmd: Box::new(ScanCommand),
+            }),
+            x if x == SendCommand.name() => Some(Command {
+                cmd: Box::new(SendCommand),
+            }),
             _ => None,
         }
     }
 
+    pub fn name(&self) -> String {
+        self.cmd.name()
+    }
+
     pub fn help_text(&self) -> String {
         format!("    {}\t{}", self.cmd.name(), self.cmd.descr())
     }
@@ -71,6 +232,11 @@ impl Command {
         self.cmd.run(args, vm)
     }
 
+    /// Gets the arguments which may follow the words typed after the command name so far.
+    pub fn next_args(&self, typed: &[&str]) -> Vec<Arg> {
+        next_args(&self.cmd.forms(), typed)
+    }
+
     pub fn list() -> Vec<Command> {
         COMMAND_NAMES
             .iter()
@@ -82,35 +248,56 @@ impl Command {
 pub trait CommandExecutor {
     fn name(&self) -> String;
     fn descr(&self) -> String;
-    fn usage(&self, with_header: bool) -> String;
-    fn required_args(&self) -> usize;
-    fn exec(&self, args: Args, vm: &mut VirtualMachine) -> Result<()>;
+    /// The heading of the usage printed by `<command> --help`.
+    fn title(&self) -> String;
+    /// The forms of arguments the command accepts, tried in order.
+    fn forms(&self) -> Vec<Vec<Arg>>;
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()>;
+
+    /// Notes printed below the usage, ahead of how the numbers it takes may be written.
+    fn notes(&self) -> String {
+        String::new()
+    }
+
+    fn usage(&self, with_header: bool) -> String {
+        let mut usage = String::new();
+
+        if with_header {
+            usage += &format!("{}\n\n", self.title());
+        }
+        usage += &format!("Usage:\n    {} --help", self.name());
+        for form in self.forms() {
+            usage += &format!("\n    {}", self.name());
+            for arg in form {
+                usage += &format!(" {}", arg.usage());
+            }
+        }
+        if !self.notes().is_empty() {
+            usage += &format!("\n\n{}", self.notes());
+        }
+        if let Some(notation) = notation(&self.forms()) {
+            usage += &format!("\n\n{}", notation);
+        }
+        usage
+    }
 
     fn print_usage(&self, with_header: bool) {
         println!("{}\n", self.usage(with_header));
     }
 
     fn run(&self, args: Args, vm: &mut VirtualMachine) -> Result<()> {
-        if !args.is_empty() && args[0] == "--help" {
+        if args.words.first().is_some_and(|x| x == "--help") {
             self.print_usage(true);
             return Ok(());
         }
-        if args.len() < self.required_args() {
-            println!("Not enough arguments given.\n");
-            self.print_usage(false);
-            return Ok(());
-        }
 
-        self.exec(args, vm)
-    }
-}
-
-pub fn reg_idx_from_str(s: &str) -> Option<u16> {
-    REG_NAMES.iter().enumerate().fold(None, |acc, (i, name)| {
-        if &s == name {
-            Some(i as u16 + 0x8000)
-        } else {
-            acc
+        match parse_args(&self.forms(), &args.words) {
+            Ok(values) => self.exec(values.with_raw(args.raw), vm),
+            Err(msg) => {
+                println!("{}\n", msg);
+                self.print_usage(false);
+                Ok(())
+            }
         }
-    })
+    }
 }
@@ -0,0 +1,47 @@
+use crate::{
+    vm::debugger::Resume,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    vm::op::Op,
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct NextCommand;
+
+impl CommandExecutor for NextCommand {
+    fn name(&self) -> String {
+        "next".into()
+    }
+
+    fn descr(&self) -> String {
+        "Executes a single instruction, stepping over calls".into()
+    }
+
+    fn title(&self) -> String {
+        "Step Over".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
+    }
+
+    fn notes(&self) -> String {
+        "Executes a single instruction, then pauses again. A CALL is run until it returns.".into()
+    }
+
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let resume = if vm.at_op(Op::CALL) {
+            Resume::Next {
+                addr: vm.pc() + 2,
+                depth: vm.stack.len(),
+            }
+        } else {
+            Resume::Step
+        };
+
+        if !vm.resume(resume) {
+            println!("The VM is not paused.");
+        }
+        Ok(())
+    }
+}
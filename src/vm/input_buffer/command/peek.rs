@@ -0,0 +1,70 @@
+use crate::{
+    constants::*,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+/// The number of words shown on each line of the dump.
+const WORDS_PER_LINE: usize = 8;
+
+#[derive(Clone, Copy)]
+pub struct PeekCommand;
+
+impl CommandExecutor for PeekCommand {
+    fn name(&self) -> String {
+        "peek".into()
+    }
+
+    fn descr(&self) -> String {
+        "Dump memory as hex and ASCII".into()
+    }
+
+    fn title(&self) -> String {
+        "Dump Memory".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![
+            Arg::address("address"),
+            Arg::count("length").optional(),
+        ]]
+    }
+
+    fn notes(&self) -> String {
+        "Dumps [length] words of memory starting at <address>, 8 by default.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let addr = args.address("address").unwrap();
+        let len = args.count("length").unwrap_or(WORDS_PER_LINE);
+        let end = addr.saturating_add(len).min(MEM_ADDR_SPACE);
+
+        for line in (addr..end).step_by(WORDS_PER_LINE) {
+            let words = (line..(line + WORDS_PER_LINE).min(end))
+                .filter_map(|a| vm.peek_mem(a))
+                .collect::<Vec<_>>();
+
+            let hex = words
+                .iter()
+                .map(|x| format!("{:04x}", x))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = words
+                .iter()
+                .map(|&x| match x {
+                    0x20..=0x7e => x as u8 as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+
+            vm.print_line(&format!(
+                "{:#06x}:  {:<width$}  {}",
+                line,
+                hex,
+                ascii,
+                width = WORDS_PER_LINE * 5 - 1
+            ))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    constants::*,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct PokeCommand;
+
+impl CommandExecutor for PokeCommand {
+    fn name(&self) -> String {
+        "poke".into()
+    }
+
+    fn descr(&self) -> String {
+        "Write values to memory".into()
+    }
+
+    fn title(&self) -> String {
+        "Write Memory".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::address("address"), Arg::word("value").repeated()]]
+    }
+
+    fn notes(&self) -> String {
+        "Writes each value to consecutive words of memory starting at <address>.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let addr = args.address("address").unwrap();
+        let values = args.values("value");
+
+        if addr + values.len() > MEM_ADDR_SPACE {
+            return vm.print_line(&format!(
+                "Writing {} words at {:#06x} runs past the end of memory",
+                values.len(),
+                addr
+            ));
+        }
+
+        vm.edit(|vm| {
+            values
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, &val)| vm.poke_mem(addr + i, val))
+        })?;
+        vm.print_line(&format!("Wrote {} words at {:#06x}", values.len(), addr))
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct PopCommand;
+
+impl CommandExecutor for PopCommand {
+    fn name(&self) -> String {
+        "pop".into()
+    }
+
+    fn descr(&self) -> String {
+        "Pop values off the stack".into()
+    }
+
+    fn title(&self) -> String {
+        "Pop off Stack".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::count("count").optional()]]
+    }
+
+    fn notes(&self) -> String {
+        "Pops [count] values off the stack, 1 by default, and prints them from the top down.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let count = args.count("count").unwrap_or(1);
+
+        if count > vm.stack.len() {
+            return vm.print_line(&format!(
+                "Cannot pop {} values off a stack of {}",
+                count,
+                vm.stack.len()
+            ));
+        }
+
+        let popped = vm.edit(|vm| {
+            let len = vm.stack.len();
+            vm.stack.split_off(len - count)
+        });
+        vm.print_line(&format!(
+            "Popped: [{}]",
+            popped
+                .iter()
+                .rev()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ))
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     constants::*,
-    vm::input_buffer::command::{reg_idx_from_str, Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     Result, VirtualMachine,
 };
 
@@ -16,53 +16,44 @@ impl CommandExecutor for PrintRegisterCommand {
         "Print the value of registers".into()
     }
 
-    fn usage(&self, with_header: bool) -> String {
-        format!(
-            "{}\
-Usage:
-    printreg --help
-    printreg <register|--all>\
-    ",
-            if with_header {
-                "Print Register Value\n\n"
-            } else {
-                ""
-            }
-        )
+    fn title(&self) -> String {
+        "Print Register Value".into()
     }
 
-    fn required_args(&self) -> usize {
-        1
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::keyword("--all")], vec![Arg::register("register")]]
     }
 
-    fn exec(&self, args: Args, vm: &mut VirtualMachine) -> Result<()> {
-        if args[0] == "--all" {
-            println!(
-                "Register values: {}",
-                REG_NAMES
-                    .iter()
-                    .enumerate()
-                    .fold(String::new(), |acc, (i, x)| format!(
-                        "{}, {}: {:04x}",
-                        acc,
-                        x,
-                        vm.read_reg(i as u16 + 0x8000).unwrap(),
-                    ))
-            );
-            return Ok(());
-        }
+    fn notes(&self) -> String {
+        "Values are printed in hex, as accepted by `setreg`.".into()
+    }
 
-        let register = &args[0];
-        let register_idx = match reg_idx_from_str(register) {
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let register_idx = match args.register("register") {
             Some(x) => x,
             None => {
-                println!("Invalid register given: {}", register);
-                println!("Possible register values: {}", REG_NAMES.join(", "));
+                println!(
+                    "Register values: {}",
+                    REG_NAMES
+                        .iter()
+                        .enumerate()
+                        .map(|(i, x)| format!(
+                            "{}: {:#06x}",
+                            x,
+                            vm.read_reg(i as u16 + 0x8000).unwrap()
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
                 return Ok(());
             }
         };
 
-        println!("Register {}: {:04x}", register, vm.read_reg(register_idx)?);
+        println!(
+            "Register {}: {:#06x}",
+            REG_NAMES[(register_idx - 0x8000) as usize],
+            vm.read_reg(register_idx)?
+        );
         Ok(())
     }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    vm::input_buffer::command::{Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     Result, VirtualMachine,
 };
 
@@ -15,26 +15,15 @@ impl CommandExecutor for PrintStackCommand {
         "Print the value of the stack".into()
     }
 
-    fn usage(&self, with_header: bool) -> String {
-        format!(
-            "{}\
-Usage:
-    printstack --help
-    printstack \
-    ",
-            if with_header {
-                "Print Stack Value\n\n"
-            } else {
-                ""
-            }
-        )
+    fn title(&self) -> String {
+        "Print Stack Value".into()
     }
 
-    fn required_args(&self) -> usize {
-        0
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
     }
 
-    fn exec(&self, _: Args, vm: &mut VirtualMachine) -> Result<()> {
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
         println!(
             "Stack values: [{}]",
             vm.stack
@@ -0,0 +1,73 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct ProfileCommand;
+
+impl CommandExecutor for ProfileCommand {
+    fn name(&self) -> String {
+        "profile".into()
+    }
+
+    fn descr(&self) -> String {
+        "Profiles executed instructions and subroutines".into()
+    }
+
+    fn title(&self) -> String {
+        "Profile Execution".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![],
+            vec![Arg::keyword("on")],
+            vec![Arg::keyword("off")],
+            vec![Arg::keyword("report"), Arg::path("out_file").optional()],
+        ]
+    }
+
+    fn notes(&self) -> String {
+        "Reports are written as JSON if the out_file has a `.json` extension, and as text otherwise."
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        match args.subcommand() {
+            Some("on") => {
+                vm.start_profiling();
+                println!("Profiling started");
+            }
+
+            Some("off") => {
+                vm.stop_profiling();
+                println!("Profiling stopped");
+            }
+
+            Some(_) => {
+                let report = match vm.profile_report() {
+                    Some(x) => x,
+                    None => {
+                        println!("Profiling is off. Use `profile on` to start.");
+                        return Ok(());
+                    }
+                };
+
+                match args.path("out_file") {
+                    Some(out_path) => report.write_to_file(out_path)?,
+                    None => println!("{}", report.to_text()),
+                }
+            }
+
+            None => {
+                if vm.is_profiling() {
+                    println!("Profiling is on.");
+                } else {
+                    println!("Profiling is off.");
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct PushCommand;
+
+impl CommandExecutor for PushCommand {
+    fn name(&self) -> String {
+        "push".into()
+    }
+
+    fn descr(&self) -> String {
+        "Push values onto the stack".into()
+    }
+
+    fn title(&self) -> String {
+        "Push onto Stack".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::value("value").repeated()]]
+    }
+
+    fn notes(&self) -> String {
+        "Pushes each value in order, leaving the last on top.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let values = args.values("value");
+
+        vm.edit(|vm| vm.stack.extend(values));
+        vm.print_line(&format!("Stack depth: {}", vm.stack.len()))
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    vm::undo::DEFAULT_UNDO_CAPACITY,
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct ReverseCommand;
+
+impl CommandExecutor for ReverseCommand {
+    fn name(&self) -> String {
+        "reverse".into()
+    }
+
+    fn descr(&self) -> String {
+        "Records executed instructions so they can be stepped back over".into()
+    }
+
+    fn title(&self) -> String {
+        "Record Undo Log".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![],
+            vec![Arg::keyword("on"), Arg::count("capacity").optional()],
+            vec![Arg::keyword("off")],
+        ]
+    }
+
+    fn notes(&self) -> String {
+        format!(
+            "Only the last `capacity` instructions are kept, {} by default. Use `stepback` and `rcontinue` to
+step backwards once recording.",
+            DEFAULT_UNDO_CAPACITY
+        )
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        match args.subcommand() {
+            Some("on") => {
+                let capacity = args.count("capacity").unwrap_or(DEFAULT_UNDO_CAPACITY);
+
+                vm.record_undo_log(capacity);
+                println!("Recording the last {} instructions", capacity);
+            }
+
+            Some(_) => {
+                vm.stop_undo_log();
+                println!("Undo log discarded");
+            }
+
+            None => match vm.undo_capacity() {
+                Some(capacity) => println!(
+                    "Recording undo log: {} of {} instructions",
+                    vm.undo_history_len(),
+                    capacity
+                ),
+                None => println!("No undo log is being recorded."),
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    vm::ReverseOutcome,
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct ReverseContinueCommand;
+
+impl CommandExecutor for ReverseContinueCommand {
+    fn name(&self) -> String {
+        "rcontinue".into()
+    }
+
+    fn descr(&self) -> String {
+        "Steps backwards to the previous breakpoint or watchpoint".into()
+    }
+
+    fn title(&self) -> String {
+        "Reverse Continue".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
+    }
+
+    fn notes(&self) -> String {
+        "Steps backwards to the previous breakpoint or watchpoint. Requires `reverse on`.".into()
+    }
+
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        if !vm.debugger.paused {
+            println!("The VM is not paused.");
+            return Ok(());
+        }
+
+        match vm.reverse_continue() {
+            ReverseOutcome::Breakpoint(addr) => println!("Breakpoint hit at {:#06x}", addr),
+            ReverseOutcome::Watchpoint(hit) => println!("{}", hit),
+            ReverseOutcome::HistoryExhausted => {
+                println!("No more instructions to step back over.")
+            }
+        }
+
+        println!("{:#06x}:\t{}", vm.pc(), vm.disassemble_instruction(vm.pc()));
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     error::Error,
-    vm::input_buffer::command::{Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     vm::VirtualMachineState,
     Result, VirtualMachine,
 };
@@ -18,27 +18,16 @@ impl CommandExecutor for SaveCommand {
         "Saves the vm state to a file".into()
     }
 
-    fn usage(&self, with_header: bool) -> String {
-        format!(
-            "{}\
-Usage:
-    save --help
-    save <out_file>\
-    ",
-            if with_header {
-                "Save Program State\n\n"
-            } else {
-                ""
-            }
-        )
+    fn title(&self) -> String {
+        "Save Program State".into()
     }
 
-    fn required_args(&self) -> usize {
-        1
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::path("out_file")]]
     }
 
-    fn exec(&self, args: Args, vm: &mut VirtualMachine) -> Result<()> {
-        let out_path = &args[0];
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let out_path = args.path("out_file").unwrap();
         let state = VirtualMachineState::new(vm);
 
         match ron::to_string(&state) {
@@ -0,0 +1,98 @@
+use crate::{
+    vm::{
+        input_buffer::command::{Arg, ArgValues, CommandExecutor},
+        scan::MemoryScan,
+    },
+    Result, VirtualMachine,
+};
+
+/// The number of candidates listed by `scan list` unless given a count.
+const DEFAULT_LIST_COUNT: usize = 20;
+
+#[derive(Clone, Copy)]
+pub struct ScanCommand;
+
+impl CommandExecutor for ScanCommand {
+    fn name(&self) -> String {
+        "scan".into()
+    }
+
+    fn descr(&self) -> String {
+        "Scans memory for the addresses of game variables".into()
+    }
+
+    fn title(&self) -> String {
+        "Scan Memory".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![Arg::keyword("eq"), Arg::value("value")],
+            vec![Arg::keyword("all")],
+            vec![Arg::keyword("changed")],
+            vec![Arg::keyword("unchanged")],
+            vec![Arg::keyword("list"), Arg::count("count").optional()],
+            vec![Arg::keyword("reset")],
+        ]
+    }
+
+    fn notes(&self) -> String {
+        "Start a scan with `scan eq <value>`, which finds every address holding <value>, or with `scan all`
+when the value is unknown. Then let the game state move on and narrow the candidates down with
+`scan eq <value>`, `scan changed` or `scan unchanged`, comparing each address with the value it
+held at the previous scan. `scan list` prints the first [count] candidates, 20 by default, and
+`scan reset` discards the scan."
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        match args.subcommand() {
+            Some("eq") => {
+                let val = args.value("value").unwrap();
+
+                match vm.scan.as_mut() {
+                    Some(scan) => scan.narrow(&vm.mem, |_, new| new == val),
+                    None => vm.scan = Some(MemoryScan::new(&vm.mem, |x| x == val)),
+                }
+            }
+            Some("all") => vm.scan = Some(MemoryScan::new(&vm.mem, |_| true)),
+            Some(x @ "changed") | Some(x @ "unchanged") => {
+                let changed = x == "changed";
+
+                match vm.scan.as_mut() {
+                    Some(scan) => scan.narrow(&vm.mem, |old, new| (old != new) == changed),
+                    None => {
+                        println!("No scan in progress. Start one with `scan eq` or `scan all`.");
+                        return Ok(());
+                    }
+                }
+            }
+            Some("list") => {
+                let count = args.count("count").unwrap_or(DEFAULT_LIST_COUNT);
+
+                match vm.scan.as_ref() {
+                    Some(scan) => {
+                        for &(addr, val) in scan.candidates().iter().take(count) {
+                            println!("{:#06x}:\t{:#06x}\t{}", addr, val, val);
+                        }
+                        if scan.candidates().len() > count {
+                            println!("... and {} more", scan.candidates().len() - count);
+                        }
+                    }
+                    None => println!("No scan in progress."),
+                }
+                return Ok(());
+            }
+            _ => {
+                vm.scan = None;
+                println!("Scan discarded.");
+                return Ok(());
+            }
+        }
+
+        if let Some(scan) = vm.scan.as_ref() {
+            println!("{} candidate addresses", scan.candidates().len());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{
+    self_modify::SelfModifyMode,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct SelfModifyCommand;
+
+impl CommandExecutor for SelfModifyCommand {
+    fn name(&self) -> String {
+        "selfmod".into()
+    }
+
+    fn descr(&self) -> String {
+        "Detects instructions which modify code".into()
+    }
+
+    fn title(&self) -> String {
+        "Detect Self-Modifying Code".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![],
+            vec![Arg::keyword("warn")],
+            vec![Arg::keyword("break")],
+            vec![Arg::keyword("off")],
+            vec![Arg::keyword("report"), Arg::path("out_file").optional()],
+        ]
+    }
+
+    fn notes(&self) -> String {
+        "A write modifies code if it targets a previously executed address, or the basic block being
+executed. `warn` prints a warning the first time each address is modified by an instruction, while
+`break` stops execution on every such write."
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        match args.subcommand() {
+            Some("warn") => {
+                vm.start_self_modify_detection(SelfModifyMode::Warn);
+                println!("Warning on self-modifying code");
+            }
+
+            Some("break") => {
+                vm.start_self_modify_detection(SelfModifyMode::Break);
+                println!("Breaking on self-modifying code");
+            }
+
+            Some("off") => {
+                vm.stop_self_modify_detection();
+                println!("Self-modifying code detection stopped");
+            }
+
+            Some(_) => match (vm.self_modify_report(), args.path("out_file")) {
+                (Some(report), Some(out_path)) => report.write_to_file(out_path)?,
+                (Some(report), None) => print!("{}", report),
+                (None, _) => println!("Self-modifying code is not being detected."),
+            },
+
+            None => match vm.self_modify_mode() {
+                Some(SelfModifyMode::Warn) => println!("Warning on self-modifying code."),
+                Some(SelfModifyMode::Break) => println!("Breaking on self-modifying code."),
+                None => println!("Self-modifying code is not being detected."),
+            },
+        }
+        Ok(())
+    }
+}
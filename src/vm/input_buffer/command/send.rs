@@ -0,0 +1,36 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct SendCommand;
+
+impl CommandExecutor for SendCommand {
+    fn name(&self) -> String {
+        "send".into()
+    }
+
+    fn descr(&self) -> String {
+        "Sends a line to the program as input".into()
+    }
+
+    fn title(&self) -> String {
+        "Send Program Input".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::text("text").optional().repeated()]]
+    }
+
+    fn notes(&self) -> String {
+        "Gives [text...] to the program as its next line of input, even if it starts with the name of a
+command, as in `send exit`. The text is sent exactly as typed, spacing included."
+            .into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        vm.input_buffer.send(args.raw().into());
+        Ok(())
+    }
+}
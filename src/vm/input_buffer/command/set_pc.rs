@@ -0,0 +1,38 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct SetPcCommand;
+
+impl CommandExecutor for SetPcCommand {
+    fn name(&self) -> String {
+        "setpc".into()
+    }
+
+    fn descr(&self) -> String {
+        "Set the program counter to a given address".into()
+    }
+
+    fn title(&self) -> String {
+        "Set Program Counter".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::address("address")]]
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let addr = args.address("address").unwrap();
+
+        vm.set_pc(addr);
+        // Like `continue`, let the instruction run even if it has a breakpoint.
+        vm.debugger.ignore_breakpoint_at(addr);
+        vm.print_line(&format!(
+            "{:#06x}:\t{}",
+            addr,
+            vm.disassemble_instruction(addr)
+        ))
+    }
+}
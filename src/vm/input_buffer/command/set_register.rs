@@ -1,6 +1,5 @@
 use crate::{
-    constants::*,
-    vm::input_buffer::command::{reg_idx_from_str, Args, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
     Result, VirtualMachine,
 };
 
@@ -16,43 +15,18 @@ impl CommandExecutor for SetRegisterCommand {
         "Set a register to a given value".into()
     }
 
-    fn usage(&self, with_header: bool) -> String {
-        format!(
-            "{}\
-Usage:
-    setreg --help
-    setreg <register> <value>\
-    ",
-            if with_header {
-                "Set Register Value\n\n"
-            } else {
-                ""
-            }
-        )
+    fn title(&self) -> String {
+        "Set Register Value".into()
     }
 
-    fn required_args(&self) -> usize {
-        2
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::register("register"), Arg::value("value")]]
     }
 
-    fn exec(&self, args: Args, vm: &mut VirtualMachine) -> Result<()> {
-        let register = &args[0];
-        let value: u16 = match args[1].parse() {
-            Ok(x) => x,
-            _ => {
-                println!("Could not parse \"{}\" as u16", args[1]);
-                return Ok(());
-            }
-        };
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let register_idx = args.register("register").unwrap();
+        let value = args.value("value").unwrap();
 
-        let register_idx = match reg_idx_from_str(register) {
-            Some(x) => x,
-            None => {
-                println!("Invalid register given: {}", register);
-                println!("Possible register values: {}", REG_NAMES.join(", "));
-                return Ok(());
-            }
-        };
-        vm.write_reg(register_idx, value)
+        vm.edit(|vm| vm.write_reg(register_idx, value))
     }
 }
@@ -0,0 +1,37 @@
+use crate::{
+    vm::debugger::Resume,
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct StepCommand;
+
+impl CommandExecutor for StepCommand {
+    fn name(&self) -> String {
+        "step".into()
+    }
+
+    fn descr(&self) -> String {
+        "Executes a single instruction".into()
+    }
+
+    fn title(&self) -> String {
+        "Step".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
+    }
+
+    fn notes(&self) -> String {
+        "Executes a single instruction, then pauses again.".into()
+    }
+
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        if !vm.resume(Resume::Step) {
+            println!("The VM is not paused.");
+        }
+        Ok(())
+    }
+}
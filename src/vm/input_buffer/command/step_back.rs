@@ -0,0 +1,46 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct StepBackCommand;
+
+impl CommandExecutor for StepBackCommand {
+    fn name(&self) -> String {
+        "stepback".into()
+    }
+
+    fn descr(&self) -> String {
+        "Reverts the last executed instructions".into()
+    }
+
+    fn title(&self) -> String {
+        "Step Backwards".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::count("count").optional()]]
+    }
+
+    fn notes(&self) -> String {
+        "Requires the undo log to be recorded with `reverse on`.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        if !vm.debugger.paused {
+            println!("The VM is not paused.");
+            return Ok(());
+        }
+
+        for _ in 0..args.count("count").unwrap_or(1) {
+            if !vm.step_back() {
+                println!("No more instructions to step back over.");
+                break;
+            }
+        }
+
+        println!("{:#06x}:\t{}", vm.pc(), vm.disassemble_instruction(vm.pc()));
+        Ok(())
+    }
+}
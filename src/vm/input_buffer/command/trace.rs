@@ -0,0 +1,48 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct TraceCommand;
+
+impl CommandExecutor for TraceCommand {
+    fn name(&self) -> String {
+        "trace".into()
+    }
+
+    fn descr(&self) -> String {
+        "Records executed instructions to a trace file".into()
+    }
+
+    fn title(&self) -> String {
+        "Record Execution Trace".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::keyword("off")], vec![Arg::path("out_file")]]
+    }
+
+    fn notes(&self) -> String {
+        "Render a trace file as text with `synacor-vm trace <trace_file>`.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let out_path = match args.path("out_file") {
+            Some(x) => x,
+            None => {
+                if vm.is_tracing() {
+                    vm.stop_trace()?;
+                    println!("Tracing stopped");
+                } else {
+                    println!("No trace is being recorded.");
+                }
+                return Ok(());
+            }
+        };
+
+        vm.trace_to(out_path)?;
+        println!("Tracing to {}", out_path);
+        Ok(())
+    }
+}
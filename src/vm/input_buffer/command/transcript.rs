@@ -0,0 +1,48 @@
+use crate::{
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct TranscriptCommand;
+
+impl CommandExecutor for TranscriptCommand {
+    fn name(&self) -> String {
+        "transcript".into()
+    }
+
+    fn descr(&self) -> String {
+        "Records every line of input to a replayable transcript".into()
+    }
+
+    fn title(&self) -> String {
+        "Record Session Transcript".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![Arg::keyword("off")], vec![Arg::path("out_file")]]
+    }
+
+    fn notes(&self) -> String {
+        "Replay a transcript with `synacor-vm run <infile> --replay <transcript_file>`.".into()
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let out_path = match args.path("out_file") {
+            Some(x) => x,
+            None => {
+                if vm.is_recording_transcript() {
+                    vm.stop_transcript();
+                    println!("Transcript recording stopped");
+                } else {
+                    println!("No transcript is being recorded.");
+                }
+                return Ok(());
+            }
+        };
+
+        vm.record_transcript(out_path)?;
+        println!("Recording transcript to {}", out_path);
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    vm::debugger::watch_target_name,
+    vm::input_buffer::command::{
+        watch::{parse_watch_args, WATCH_KINDS},
+        Arg, ArgValues, CommandExecutor,
+    },
+    Result, VirtualMachine,
+};
+
+#[derive(Clone, Copy)]
+pub struct UnwatchCommand;
+
+impl CommandExecutor for UnwatchCommand {
+    fn name(&self) -> String {
+        "unwatch".into()
+    }
+
+    fn descr(&self) -> String {
+        "Delete a watchpoint".into()
+    }
+
+    fn title(&self) -> String {
+        "Delete Watchpoint".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![
+            Arg::choice("kind", WATCH_KINDS),
+            Arg::target("target"),
+        ]]
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let (kind, addr) = parse_watch_args(&args).unwrap();
+
+        if vm.watchpoints_mut().remove(addr, kind) {
+            println!(
+                "Watchpoint ({}) on {} deleted",
+                kind,
+                watch_target_name(addr)
+            );
+        } else {
+            println!(
+                "No watchpoint ({}) set on {}",
+                kind,
+                watch_target_name(addr)
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    vm::input_buffer::command::{Args, Command, CommandExecutor},
+    vm::input_buffer::command::{Arg, ArgValues, Command, CommandExecutor},
     Result, VirtualMachine,
 };
 
@@ -15,6 +15,14 @@ impl CommandExecutor for VmHelpCommand {
         "Prints this help text".into()
     }
 
+    fn title(&self) -> String {
+        "Synacor VM Shell".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![vec![]]
+    }
+
     fn usage(&self, _: bool) -> String {
         format!(
             "\
@@ -33,11 +41,14 @@ Commands:
         )
     }
 
-    fn required_args(&self) -> usize {
-        0
-    }
-
-    fn exec(&self, _: Args, _: &mut VirtualMachine) -> Result<()> {
-        Ok(self.print_usage(true))
+    fn exec(&self, _: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        self.print_usage(true);
+        if let Some(prefix) = vm.command_prefix() {
+            println!(
+                "Commands given at the program prompt must start with `{}`.\n",
+                prefix
+            );
+        }
+        Ok(())
     }
 }
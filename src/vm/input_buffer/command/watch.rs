@@ -0,0 +1,71 @@
+use crate::{
+    vm::debugger::{watch_target_name, WatchKind},
+    vm::input_buffer::command::{Arg, ArgValues, CommandExecutor},
+    Result, VirtualMachine,
+};
+
+/// The kinds of watchpoint, by name.
+pub const WATCH_KINDS: &[&str] = &["read", "write", "change"];
+
+#[derive(Clone, Copy)]
+pub struct WatchCommand;
+
+impl CommandExecutor for WatchCommand {
+    fn name(&self) -> String {
+        "watch".into()
+    }
+
+    fn descr(&self) -> String {
+        "Set or list memory and register watchpoints".into()
+    }
+
+    fn title(&self) -> String {
+        "Manage Watchpoints".into()
+    }
+
+    fn forms(&self) -> Vec<Vec<Arg>> {
+        vec![
+            vec![],
+            vec![Arg::choice("kind", WATCH_KINDS), Arg::target("target")],
+        ]
+    }
+
+    fn exec(&self, args: ArgValues, vm: &mut VirtualMachine) -> Result<()> {
+        let (kind, addr) = match parse_watch_args(&args) {
+            Some(x) => x,
+            None => {
+                if vm.watchpoints().is_empty() {
+                    println!("No watchpoints set.");
+                }
+                for (addr, kind, wp) in vm.watchpoints().iter() {
+                    println!(
+                        "{}\t{}\t{}\thits: {}",
+                        watch_target_name(addr),
+                        kind,
+                        if wp.enabled { "enabled" } else { "disabled" },
+                        wp.hits
+                    );
+                }
+                return Ok(());
+            }
+        };
+
+        if vm.watchpoints_mut().add(addr, kind) {
+            println!("Watchpoint ({}) set on {}", kind, watch_target_name(addr));
+        } else {
+            println!(
+                "Watchpoint ({}) already set on {}",
+                kind,
+                watch_target_name(addr)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Gets the kind and target of a watchpoint from the `<kind> <target>` arguments.
+pub fn parse_watch_args(args: &ArgValues) -> Option<(WatchKind, u16)> {
+    let kind = WatchKind::from_name(args.choice("kind")?)?;
+
+    args.target("target").map(|addr| (kind, addr))
+}
@@ -1,7 +1,17 @@
 mod command;
 
-use self::command::{Args, Command};
-use std::io::{self, Write};
+pub(crate) use self::command::{ArgKind, Command};
+
+use self::command::Args;
+use crate::vm::{
+    io::{InputSource, MemoryInput, OutputSink},
+    transcript::TranscriptWriter,
+};
+use std::io;
+
+/// The prefix which lines given at the program prompt must start with to name a command, unless
+/// set otherwise.
+pub const DEFAULT_COMMAND_PREFIX: &str = "!";
 
 #[derive(Clone, Copy, PartialEq)]
 enum InputBufferState {
@@ -9,40 +19,141 @@ enum InputBufferState {
     Standby,
 }
 
+/// The position of the input buffer within the program input, used to rewind it.
+#[derive(Clone)]
+pub struct InputCursor {
+    state: InputBufferState,
+    buffer: Vec<u8>,
+    slice_idx: usize,
+    line_no: usize,
+}
+
+/// The complete state of the input buffer, apart from its input source.
+#[derive(Clone)]
+pub struct InputSnapshot {
+    cursor: InputCursor,
+    replay: Vec<Vec<u8>>,
+}
+
 /// A helper struct for handling user input outside of the VM instructions
 pub struct InputBuffer {
     state: InputBufferState,
     buffer: Vec<u8>,
     slice_idx: usize,
+    line_no: usize,
+    replay: Vec<Vec<u8>>,
+    input: Box<dyn InputSource>,
+    transcript: Option<TranscriptWriter>,
+    command_prefix: Option<String>,
+    clock: u64,
 }
 
 impl InputBuffer {
-    /// Creates a new InputBuffer
-    pub fn new() -> Self {
+    /// Creates a new InputBuffer reading from the given input source
+    pub fn new(mut input: Box<dyn InputSource>) -> Self {
+        input.set_command_prefix(Some(DEFAULT_COMMAND_PREFIX));
+
         InputBuffer {
             state: InputBufferState::Standby,
             buffer: vec![],
             slice_idx: 0,
+            line_no: 0,
+            replay: vec![],
+            input,
+            transcript: None,
+            command_prefix: Some(DEFAULT_COMMAND_PREFIX.into()),
+            clock: 0,
         }
     }
 
-    /// Reads input from stdin and returns Command and Args if appropriate.
-    pub fn process_input(&mut self) -> io::Result<Option<(Command, Args)>> {
+    /// Creates a copy of this InputBuffer, with the same partially processed input, reading from
+    /// an empty input source.
+    pub fn fork(&self) -> Self {
+        let mut fork = InputBuffer::new(Box::new(MemoryInput::default()));
+
+        fork.restore_snapshot(self.snapshot());
+        fork.command_prefix = self.command_prefix.clone();
+        fork
+    }
+
+    /// Sets the input source, keeping any partially processed input.
+    pub fn set_input(&mut self, mut input: Box<dyn InputSource>) {
+        input.set_command_prefix(self.command_prefix.as_deref());
+        self.input = input;
+    }
+
+    /// Starts recording every line read from the input source to a transcript.
+    pub fn set_transcript(&mut self, transcript: Option<TranscriptWriter>) {
+        self.transcript = transcript;
+    }
+
+    /// Checks if lines read are being recorded to a transcript.
+    pub fn has_transcript(&self) -> bool {
+        self.transcript.is_some()
+    }
+
+    /// Sets the prefix which lines given at the program prompt must start with to name a command,
+    /// or `None` to take any line starting with the name of a command as one.
+    pub fn set_command_prefix(&mut self, prefix: Option<String>) {
+        self.input.set_command_prefix(prefix.as_deref());
+        self.command_prefix = prefix;
+    }
+
+    /// Gets the prefix of commands given at the program prompt, if set.
+    pub fn command_prefix(&self) -> Option<&str> {
+        self.command_prefix.as_deref()
+    }
+
+    /// Queues up a line to be given to the program as is, after any line already queued.
+    pub fn send(&mut self, mut line: String) {
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        self.replay.insert(0, line.into_bytes());
+    }
+
+    /// Reads a line from the input source and returns Command and Args if appropriate. The prompt
+    /// is written to the given output sink when the input source is interactive. `executed` is the
+    /// number of instructions executed so far, recorded in the transcript.
+    pub fn process_input(
+        &mut self,
+        out: &mut dyn OutputSink,
+        executed: u64,
+    ) -> io::Result<Option<(Command, Args)>> {
         if self.state != InputBufferState::Standby {
             return Ok(None);
         }
+        self.clock = executed;
 
+        // Queued lines are always program input, so they are never taken as commands.
+        let (buffer, queued) = match self.replay.pop() {
+            Some(line) => (line, true),
+            None => (self.read_line(out)?.into_bytes(), false),
+        };
+        self.buffer = buffer;
+        self.slice_idx = 0;
+        self.line_no += 1;
         self.state = InputBufferState::ProcessingInput;
 
-        self.prompt()?;
-        self.load_from_stdin()?;
-
-        let argv = self.to_words();
-        if let Some(cmd) = argv.get(0).map(|name| Command::from_name(name)).flatten() {
-            return Ok(Some((cmd, argv.into_iter().skip(1).collect())));
+        if queued {
+            return Ok(None);
         }
+        let line = self.buffer.iter().map(|&x| x as char).collect::<String>();
+        Ok(self.parse_command(&line, true))
+    }
+
+    /// Reads a line from the input source and returns Command and Args if it names a command.
+    /// Unlike `InputBuffer::process_input`, any partially consumed line of program input is left
+    /// untouched.
+    pub fn read_command(
+        &mut self,
+        out: &mut dyn OutputSink,
+        executed: u64,
+    ) -> io::Result<Option<(Command, Args)>> {
+        self.clock = executed;
+        let line = self.read_line(out)?;
 
-        Ok(None)
+        Ok(self.parse_command(&line, false))
     }
 
     /// Places the input buffer into the Standby state, where it will wait to process input at the
@@ -51,36 +162,118 @@ impl InputBuffer {
         self.state = InputBufferState::Standby;
     }
 
+    /// Captures the current position within the program input.
+    pub fn cursor(&self) -> InputCursor {
+        InputCursor {
+            state: self.state,
+            buffer: self.buffer.clone(),
+            slice_idx: self.slice_idx,
+            line_no: self.line_no,
+        }
+    }
+
+    /// Rewinds to a previously captured position. Any line read since then is queued up to be
+    /// processed again rather than being lost.
+    pub fn restore(&mut self, cursor: InputCursor) {
+        if self.line_no > cursor.line_no {
+            self.replay.push(std::mem::take(&mut self.buffer));
+        }
+
+        self.state = cursor.state;
+        self.buffer = cursor.buffer;
+        self.slice_idx = cursor.slice_idx;
+        self.line_no = cursor.line_no;
+    }
+
+    /// Captures the complete state of the buffer, including lines queued up for replay.
+    pub fn snapshot(&self) -> InputSnapshot {
+        InputSnapshot {
+            cursor: self.cursor(),
+            replay: self.replay.clone(),
+        }
+    }
+
+    /// Returns the buffer to a captured state. Unlike `InputBuffer::restore`, nothing read since
+    /// is kept, and the input source carries on from where it is.
+    pub fn restore_snapshot(&mut self, snapshot: InputSnapshot) {
+        let cursor = snapshot.cursor;
+
+        self.state = cursor.state;
+        self.buffer = cursor.buffer;
+        self.slice_idx = cursor.slice_idx;
+        self.line_no = cursor.line_no;
+        self.replay = snapshot.replay;
+    }
+
+    /// Lets the input source see a character printed by the program.
+    pub fn observe_output(&mut self, c: char) {
+        self.input.observe_output(c);
+    }
+
     /// Reads the first byte available, and sets the internal index to the next available byte.
     pub fn read_byte(&mut self) -> u8 {
         self.slice_idx += 1;
         self.buffer[self.slice_idx - 1]
     }
 
-    fn prompt(&mut self) -> io::Result<()> {
-        let mut out = io::stdout();
-        out.write(b"> ")?;
+    fn prompt(&mut self, out: &mut dyn OutputSink) -> io::Result<()> {
+        out.write_str("> ")?;
         out.flush()
     }
 
-    fn load_from_stdin(&mut self) -> io::Result<()> {
+    fn read_line(&mut self, out: &mut dyn OutputSink) -> io::Result<String> {
         let mut line = String::new();
 
-        io::stdin().read_line(&mut line)?;
-        self.buffer = line.bytes().collect();
-        self.slice_idx = 0;
+        if self.input.is_interactive() && !self.input.shows_prompt() {
+            self.prompt(out)?;
+        } else {
+            out.flush()?;
+        }
+        if self.input.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
 
-        Ok(())
-    }
+        match self.input.expected_instruction_count() {
+            Some(expected) if expected != self.clock => out.warn(&format!(
+                "transcript out of sync, {:?} was recorded at instruction {} but read at {}",
+                line.trim_end(),
+                expected,
+                self.clock
+            ))?,
+            _ => {}
+        }
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.record(self.clock, &line)?;
+        }
 
-    fn to_string(&self) -> String {
-        self.buffer.iter().map(|x| *x as char).collect()
+        Ok(line)
     }
 
-    fn to_words(&self) -> Vec<String> {
-        self.to_string()
-            .split_whitespace()
-            .map(|x| x.into())
-            .collect()
+    /// Parses a line naming a command into the Command and its Args. If a command prefix is set,
+    /// the line is stripped of it, and must start with it when `prefix_required` is true.
+    fn parse_command(&self, line: &str, prefix_required: bool) -> Option<(Command, Args)> {
+        let line = match self.command_prefix.as_ref() {
+            Some(prefix) => match line.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest,
+                None if prefix_required => return None,
+                None => line,
+            },
+            None => line,
+        };
+        let line = line.trim_start().trim_end_matches(['\r', '\n']);
+        let (name, rest) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+        // Only the whitespace separating the arguments from the command name is dropped.
+        let raw = rest.strip_prefix(char::is_whitespace).unwrap_or(rest);
+
+        Command::from_name(name).map(|cmd| {
+            let args = Args {
+                words: raw.split_whitespace().map(|x| x.into()).collect(),
+                raw: raw.into(),
+            };
+            (cmd, args)
+        })
     }
 }
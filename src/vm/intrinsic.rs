@@ -0,0 +1,82 @@
+//! Native intrinsics which replace guest subroutines with Rust code.
+//!
+//! An intrinsic is registered at a guest address with `VirtualMachine::register_intrinsic`. Every
+//! `CALL` to that address runs the intrinsic instead, which may then either return to the caller
+//! straight away, or fall back to calling the guest subroutine as normal.
+
+use crate::{constants::*, error::Error, vm::VirtualMachine, Result};
+use std::{cell::RefCell, rc::Rc};
+
+/// What the VM does once an intrinsic has run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntrinsicAction {
+    /// Return to the caller, as if the guest subroutine had run and returned.
+    Return,
+    /// Call the guest subroutine as normal. Any changes the intrinsic made are kept.
+    Fallback,
+}
+
+/// A native function registered at a guest address.
+pub(crate) type Intrinsic = Rc<RefCell<dyn FnMut(&mut GuestState<'_>) -> IntrinsicAction>>;
+
+/// The registers, memory and stack of the VM, as seen by an intrinsic. Writes and stack changes
+/// made through it are part of the `CALL` instruction, so they are traced, watched and undone like
+/// those of any other instruction.
+pub struct GuestState<'a> {
+    pub(crate) vm: &'a mut VirtualMachine,
+    pub(crate) call_site: usize,
+}
+
+impl<'a> GuestState<'a> {
+    /// Gets the address of the `CALL` instruction which called the intrinsic.
+    pub fn call_site(&self) -> usize {
+        self.call_site
+    }
+
+    /// Gets the value of the register with the given index, from `0` for `AX` to `7` for `HX`, if
+    /// in range.
+    pub fn reg(&self, idx: usize) -> Option<u16> {
+        self.vm.reg.get(idx).cloned()
+    }
+
+    /// Sets the value of the register with the given index, from `0` for `AX` to `7` for `HX`.
+    /// Fails if the index is out of range.
+    pub fn set_reg(&mut self, idx: usize, val: u16) -> Result<()> {
+        if idx >= REG_NAMES.len() {
+            return Err(Error::InvalidRegister {
+                pc: self.call_site,
+                register: FIFTEEN_BIT_MODULO.saturating_add(idx.min(0x7fff) as u16),
+            });
+        }
+        self.vm.write_reg(FIFTEEN_BIT_MODULO + idx as u16, val)
+    }
+
+    /// Reads the value at the given memory address, if in bounds.
+    pub fn read_mem(&self, addr: u16) -> Option<u16> {
+        self.vm.mem.get(addr as usize).cloned()
+    }
+
+    /// Writes the value to the given memory address.
+    pub fn write_mem(&mut self, addr: u16, val: u16) -> Result<()> {
+        if VirtualMachine::is_reg(addr) {
+            return Err(Error::MemOutOfBoundsAccess { pc: self.call_site });
+        }
+        self.vm.write(addr, val)
+    }
+
+    /// Gets the stack, with the return address of the call on top.
+    pub fn stack(&self) -> &[u16] {
+        &self.vm.stack
+    }
+
+    /// Pushes a value onto the stack. The value is on top of the return address, so it must be
+    /// popped again before returning to the caller.
+    pub fn push(&mut self, val: u16) {
+        self.vm.stack.push(val);
+    }
+
+    /// Pops the value off the top of the stack, if any.
+    pub fn pop(&mut self) -> Option<u16> {
+        self.vm.stack.pop()
+    }
+}
@@ -0,0 +1,103 @@
+use crate::vm::io::InputSource;
+use std::io;
+
+/// Reads player input from one source until it is exhausted, then carries on with another.
+pub struct ChainInput {
+    first: Box<dyn InputSource>,
+    second: Box<dyn InputSource>,
+    /// The line following the last one read from `first`, along with the instruction count it is
+    /// expected at. It is read ahead so that the switch to `second` is made as soon as `first` is
+    /// exhausted, and reported by `is_interactive` and `shows_prompt` before the next read.
+    next: Option<(String, Option<u64>)>,
+    /// The instruction count expected for the last line read from `first`.
+    expected: Option<u64>,
+    first_done: bool,
+    reading_second: bool,
+}
+
+impl ChainInput {
+    /// Creates a new ChainInput reading from `first`, then from `second`.
+    pub fn new<A, B>(first: A, second: B) -> Self
+    where
+        A: InputSource + 'static,
+        B: InputSource + 'static,
+    {
+        ChainInput {
+            first: Box::new(first),
+            second: Box::new(second),
+            next: None,
+            expected: None,
+            first_done: false,
+            reading_second: false,
+        }
+    }
+
+    /// Reads a line from `first`, or returns `None` if it is exhausted.
+    fn read_first(&mut self) -> io::Result<Option<(String, Option<u64>)>> {
+        let mut line = String::new();
+
+        match self.first.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some((line, self.first.expected_instruction_count()))),
+        }
+    }
+
+    /// The source the next line will be read from.
+    fn current(&self) -> &dyn InputSource {
+        if self.first_done {
+            &*self.second
+        } else {
+            &*self.first
+        }
+    }
+}
+
+impl InputSource for ChainInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        if !self.first_done {
+            let line = match self.next.take() {
+                Some(x) => Some(x),
+                None => self.read_first()?,
+            };
+
+            if let Some((line, expected)) = line {
+                self.next = self.read_first()?;
+                self.first_done = self.next.is_none();
+                self.expected = expected;
+
+                buf.push_str(&line);
+                return Ok(line.len());
+            }
+            self.first_done = true;
+        }
+
+        self.reading_second = true;
+        self.second.read_line(buf)
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.current().is_interactive()
+    }
+
+    fn shows_prompt(&self) -> bool {
+        self.current().shows_prompt()
+    }
+
+    fn expected_instruction_count(&self) -> Option<u64> {
+        if self.reading_second {
+            self.second.expected_instruction_count()
+        } else {
+            self.expected
+        }
+    }
+
+    fn observe_output(&mut self, c: char) {
+        self.first.observe_output(c);
+        self.second.observe_output(c);
+    }
+
+    fn set_command_prefix(&mut self, prefix: Option<&str>) {
+        self.first.set_command_prefix(prefix);
+        self.second.set_command_prefix(prefix);
+    }
+}
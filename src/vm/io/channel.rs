@@ -0,0 +1,56 @@
+use crate::vm::io::{InputSource, OutputSink};
+use std::{
+    io,
+    sync::mpsc::{Receiver, Sender},
+};
+
+/// Receives lines of player input over a channel. Each message is treated as a single line, and a
+/// trailing newline is added if it is missing. The input is exhausted once every sender has been
+/// dropped.
+pub struct ChannelInput {
+    receiver: Receiver<String>,
+}
+
+impl ChannelInput {
+    /// Creates a new ChannelInput from the receiving end of a channel.
+    pub fn new(receiver: Receiver<String>) -> Self {
+        ChannelInput { receiver }
+    }
+}
+
+impl InputSource for ChannelInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        match self.receiver.recv() {
+            Ok(mut line) => {
+                if !line.ends_with('\n') {
+                    line.push('\n');
+                }
+                buf.push_str(&line);
+                Ok(line.len())
+            }
+            Err(_) => Ok(0),
+        }
+    }
+}
+
+/// Sends each output character over a channel. Output is silently discarded once the receiver
+/// has been dropped.
+pub struct ChannelOutput {
+    sender: Sender<char>,
+}
+
+impl ChannelOutput {
+    /// Creates a new ChannelOutput from the sending end of a channel.
+    pub fn new(sender: Sender<char>) -> Self {
+        ChannelOutput { sender }
+    }
+}
+
+impl OutputSink for ChannelOutput {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        for c in s.chars() {
+            let _ = self.sender.send(c);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{
+    constants::*,
+    vm::input_buffer::{ArgKind, Command, DEFAULT_COMMAND_PREFIX},
+    vm::io::InputSource,
+};
+use rustyline::{
+    completion::{Completer, FilenameCompleter, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+    Context, Editor, Helper,
+};
+use std::{collections::BTreeSet, io, path::PathBuf};
+
+/// The prompt shown before each line.
+const PROMPT: &str = "> ";
+
+/// Reads player input from the terminal with line editing, tab completion and a history which is
+/// kept across sessions. Completion offers shell command names, then the arguments each command
+/// accepts, such as subcommands, register names and file paths, and otherwise the words the
+/// program has printed so far, which include every verb, exit and item of the game.
+pub struct EditorInput {
+    editor: Editor<EditorHelper, DefaultHistory>,
+    history_path: Option<PathBuf>,
+}
+
+impl EditorInput {
+    /// Creates a new EditorInput, loading its history from `history_path` if it exists and
+    /// saving each line read to it.
+    pub fn new(history_path: Option<PathBuf>) -> io::Result<Self> {
+        let mut editor = Editor::new().map_err(into_io_error)?;
+
+        editor.set_helper(Some(EditorHelper::new()));
+        if let Some(path) = history_path.as_ref() {
+            // There is no history to load yet on the first session.
+            let _ = editor.load_history(path);
+        }
+
+        Ok(EditorInput {
+            editor,
+            history_path,
+        })
+    }
+}
+
+impl InputSource for EditorInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let line = loop {
+            match self.editor.readline(PROMPT) {
+                Ok(x) => break x,
+                // Ctrl-C discards the line being typed and prompts for a new one.
+                Err(ReadlineError::Interrupted) => {}
+                Err(ReadlineError::Eof) => return Ok(0),
+                Err(e) => return Err(into_io_error(e)),
+            }
+        };
+
+        if !line.trim().is_empty() {
+            self.editor
+                .add_history_entry(line.as_str())
+                .map_err(into_io_error)?;
+
+            if let Some(path) = self.history_path.as_ref() {
+                self.editor.save_history(path).map_err(into_io_error)?;
+            }
+        }
+
+        buf.push_str(&line);
+        buf.push('\n');
+        Ok(line.len() + 1)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn shows_prompt(&self) -> bool {
+        true
+    }
+
+    fn observe_output(&mut self, c: char) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.observe(c);
+        }
+    }
+
+    fn set_command_prefix(&mut self, prefix: Option<&str>) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.command_prefix = prefix.map(String::from);
+        }
+    }
+}
+
+fn into_io_error(e: ReadlineError) -> io::Error {
+    match e {
+        ReadlineError::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+/// Completes the words of a line being edited.
+struct EditorHelper {
+    commands: Vec<String>,
+    command_prefix: Option<String>,
+    words: BTreeSet<String>,
+    word: String,
+    files: FilenameCompleter,
+}
+
+impl EditorHelper {
+    fn new() -> Self {
+        EditorHelper {
+            commands: Command::list().iter().map(|cmd| cmd.name()).collect(),
+            command_prefix: Some(DEFAULT_COMMAND_PREFIX.into()),
+            words: BTreeSet::new(),
+            word: String::new(),
+            files: FilenameCompleter::new(),
+        }
+    }
+
+    /// Collects the words printed by the program, one character at a time.
+    fn observe(&mut self, c: char) {
+        if c.is_ascii_alphabetic() {
+            self.word.push(c.to_ascii_lowercase());
+        } else if !self.word.is_empty() {
+            let word = std::mem::take(&mut self.word);
+            if word.len() > 1 {
+                self.words.insert(word);
+            }
+        }
+    }
+}
+
+impl Completer for EditorHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix_len = match self.command_prefix.as_deref() {
+            Some(prefix) if line.starts_with(prefix) => prefix.len(),
+            _ => 0,
+        };
+        if pos < prefix_len {
+            return Ok((pos, vec![]));
+        }
+
+        let start = line[prefix_len..pos]
+            .rfind(' ')
+            .map_or(prefix_len, |i| prefix_len + i + 1);
+        let partial = &line[start..pos];
+        let mut words = line[prefix_len..start].split_whitespace();
+        let command = words.next().and_then(Command::from_name);
+
+        let candidates: Vec<&str> = if start == prefix_len {
+            let names = self.commands.iter().map(|x| x.as_str());
+
+            // After the command prefix, only a command may follow.
+            if prefix_len > 0 {
+                names.collect()
+            } else {
+                names.chain(self.words.iter().map(|x| x.as_str())).collect()
+            }
+        } else if let Some(command) = command {
+            let next = command.next_args(&words.collect::<Vec<_>>());
+
+            if next.iter().any(|arg| arg.kind == ArgKind::Path) {
+                return self.files.complete_path(line, pos);
+            }
+            next.iter()
+                .flat_map(|arg| match arg.kind {
+                    ArgKind::Keyword | ArgKind::Flag => vec![arg.name],
+                    ArgKind::Choice(choices) => choices.to_vec(),
+                    ArgKind::Register | ArgKind::Word | ArgKind::Target => REG_NAMES.to_vec(),
+                    _ => vec![],
+                })
+                .collect()
+        } else {
+            self.words.iter().map(|x| x.as_str()).collect()
+        };
+
+        Ok((start, pairs(candidates, partial)))
+    }
+}
+
+/// Builds the completion candidates starting with `partial`.
+fn pairs(candidates: Vec<&str>, partial: &str) -> Vec<Pair> {
+    let set = candidates
+        .into_iter()
+        .filter(|x| x.starts_with(partial))
+        .collect::<BTreeSet<_>>();
+
+    set.into_iter()
+        .map(|x| Pair {
+            display: x.into(),
+            replacement: x.into(),
+        })
+        .collect()
+}
+
+impl Hinter for EditorHelper {
+    type Hint = String;
+}
+
+impl Highlighter for EditorHelper {}
+
+impl Validator for EditorHelper {}
+
+impl Helper for EditorHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(helper: &EditorHelper, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        let (start, pairs) = helper
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+
+        (start, pairs.into_iter().map(|x| x.replacement).collect())
+    }
+
+    #[test]
+    fn completes_commands_after_the_configured_prefix() {
+        let mut helper = EditorHelper::new();
+        helper.command_prefix = Some(":".into());
+        "You see a brass lantern"
+            .chars()
+            .for_each(|c| helper.observe(c));
+
+        assert_eq!(complete(&helper, ":brea"), (1, vec!["break".into()]));
+        assert_eq!(complete(&helper, "bra"), (0, vec!["brass".into()]));
+        assert_eq!(complete(&helper, "!bre"), (0, vec![]));
+        assert_eq!(complete(&helper, ":setreg A"), (8, vec!["AX".into()]));
+        assert_eq!(complete(&helper, ":poke 0x10 B"), (11, vec!["BX".into()]));
+        assert_eq!(complete(&helper, ":push B"), (6, vec![]));
+    }
+
+    #[test]
+    fn completes_commands_and_words_without_a_prefix() {
+        let mut helper = EditorHelper::new();
+        helper.command_prefix = None;
+        "The lantern breaks."
+            .chars()
+            .for_each(|c| helper.observe(c));
+
+        assert_eq!(
+            complete(&helper, "brea"),
+            (0, vec!["break".into(), "breaks".into()])
+        );
+        assert_eq!(complete(&helper, "watch wr"), (6, vec!["write".into()]));
+    }
+}
@@ -0,0 +1,50 @@
+use crate::vm::io::{InputSource, OutputSink};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// Reads player input line by line from a file.
+pub struct FileInput {
+    reader: BufReader<File>,
+}
+
+impl FileInput {
+    /// Opens the file at the given path for reading.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(FileInput {
+            reader: BufReader::new(File::open(path)?),
+        })
+    }
+}
+
+impl InputSource for FileInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.reader.read_line(buf)
+    }
+}
+
+/// Writes output to a file, truncating it if it already exists.
+pub struct FileOutput {
+    writer: BufWriter<File>,
+}
+
+impl FileOutput {
+    /// Creates the file at the given path for writing.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(FileOutput {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl OutputSink for FileOutput {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.writer.write_all(s.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::vm::io::{InputSource, OutputSink};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// Feeds player input from an in-memory buffer.
+#[derive(Clone, Default)]
+pub struct MemoryInput {
+    buffer: VecDeque<char>,
+}
+
+impl MemoryInput {
+    /// Creates a new MemoryInput containing the given text.
+    pub fn new<S: AsRef<str>>(input: S) -> Self {
+        let mut mem = MemoryInput::default();
+        mem.push_str(input);
+        mem
+    }
+
+    /// Appends text to the end of the remaining input.
+    pub fn push_str<S: AsRef<str>>(&mut self, input: S) {
+        self.buffer.extend(input.as_ref().chars());
+    }
+}
+
+impl InputSource for MemoryInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let len = match self.buffer.iter().position(|&c| c == '\n') {
+            Some(i) => i + 1,
+            None => self.buffer.len(),
+        };
+        let line = self.buffer.drain(..len).collect::<String>();
+
+        buf.push_str(&line);
+        Ok(line.len())
+    }
+}
+
+/// Captures output into an in-memory buffer. Clones share the same buffer, so a clone can be kept
+/// to read the output after the original has been handed to the VM.
+#[derive(Clone, Default)]
+pub struct MemoryOutput {
+    buffer: Arc<Mutex<String>>,
+}
+
+impl MemoryOutput {
+    /// Creates a new, empty MemoryOutput.
+    pub fn new() -> Self {
+        MemoryOutput::default()
+    }
+
+    /// Returns a copy of everything written so far.
+    pub fn contents(&self) -> String {
+        self.lock().clone()
+    }
+
+    /// Returns everything written so far and clears the buffer.
+    pub fn take(&self) -> String {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, String> {
+        self.buffer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl OutputSink for MemoryOutput {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.lock().push_str(s);
+        Ok(())
+    }
+}
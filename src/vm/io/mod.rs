@@ -0,0 +1,103 @@
+//! Pluggable input sources and output sinks for the VirtualMachine.
+//!
+//! By default the VM reads player input from stdin and prints `OUT` characters to stdout, but any
+//! type implementing `InputSource` or `OutputSink` may be set on the VM when it is built. This makes
+//! it possible to drive the VM from code and capture its output exactly.
+
+mod chain;
+mod channel;
+mod editor;
+mod file;
+mod memory;
+mod null;
+mod stdio;
+
+pub use self::{
+    chain::ChainInput,
+    channel::{ChannelInput, ChannelOutput},
+    editor::EditorInput,
+    file::{FileInput, FileOutput},
+    memory::{MemoryInput, MemoryOutput},
+    null::NullOutput,
+    stdio::{StdinInput, StdoutOutput},
+};
+
+use std::io;
+
+/// A source of lines of player input for the `IN` instruction.
+pub trait InputSource {
+    /// Reads the next line of input, including its trailing newline, and appends it to `buf`.
+    /// Returns the number of bytes read, where `0` signals that the input is exhausted.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+
+    /// Whether a person is typing the input, in which case the VM will print a prompt before
+    /// reading each line.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    /// Whether the source prints its own prompt, in which case the VM will not print one.
+    fn shows_prompt(&self) -> bool {
+        false
+    }
+
+    /// The number of instructions which should have been executed when the line last read is
+    /// consumed, for sources replaying a recorded session.
+    fn expected_instruction_count(&self) -> Option<u64> {
+        None
+    }
+
+    /// Called with each character printed by the program, for sources which make use of it.
+    fn observe_output(&mut self, _c: char) {}
+
+    /// Called with the prefix which lines must start with to name a shell command whenever it is
+    /// set, for sources which complete commands.
+    fn set_command_prefix(&mut self, _prefix: Option<&str>) {}
+}
+
+impl<T: InputSource + ?Sized> InputSource for Box<T> {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        (**self).read_line(buf)
+    }
+
+    fn is_interactive(&self) -> bool {
+        (**self).is_interactive()
+    }
+
+    fn shows_prompt(&self) -> bool {
+        (**self).shows_prompt()
+    }
+
+    fn expected_instruction_count(&self) -> Option<u64> {
+        (**self).expected_instruction_count()
+    }
+
+    fn observe_output(&mut self, c: char) {
+        (**self).observe_output(c)
+    }
+
+    fn set_command_prefix(&mut self, prefix: Option<&str>) {
+        (**self).set_command_prefix(prefix)
+    }
+}
+
+/// A sink for the characters printed by the `OUT` instruction.
+pub trait OutputSink {
+    /// Writes the given string to the sink.
+    fn write_str(&mut self, s: &str) -> io::Result<()>;
+
+    /// Flushes any buffered output.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Writes a single character to the sink.
+    fn write_char(&mut self, c: char) -> io::Result<()> {
+        self.write_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Writes a warning from the VM, such as a self-modifying code warning, as a line of its own.
+    fn warn(&mut self, msg: &str) -> io::Result<()> {
+        self.write_str(&format!("Warning: {}\n", msg))
+    }
+}
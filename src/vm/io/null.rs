@@ -0,0 +1,12 @@
+use crate::vm::io::OutputSink;
+use std::io;
+
+/// Discards all output.
+#[derive(Clone, Copy, Default)]
+pub struct NullOutput;
+
+impl OutputSink for NullOutput {
+    fn write_str(&mut self, _: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
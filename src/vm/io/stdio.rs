@@ -0,0 +1,35 @@
+use crate::vm::io::{InputSource, OutputSink};
+use std::io::{self, Write};
+
+/// Reads player input from stdin.
+#[derive(Clone, Copy, Default)]
+pub struct StdinInput;
+
+impl InputSource for StdinInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        io::stdin().read_line(buf)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+/// Prints output to stdout.
+#[derive(Clone, Copy, Default)]
+pub struct StdoutOutput;
+
+impl OutputSink for StdoutOutput {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        io::stdout().write_all(s.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+
+    fn warn(&mut self, msg: &str) -> io::Result<()> {
+        io::stdout().flush()?;
+        writeln!(io::stderr(), "Warning: {}", msg)
+    }
+}
@@ -0,0 +1,79 @@
+use crate::constants::*;
+use std::{
+    ops::{Index, Range},
+    sync::Arc,
+};
+
+/// The number of words in a page of memory.
+const PAGE_SIZE: usize = 0x100;
+const PAGE_COUNT: usize = MEM_ADDR_SPACE / PAGE_SIZE;
+
+type Page = [u16; PAGE_SIZE];
+
+/// The memory of the VirtualMachine, split into reference-counted pages. Clones share all of
+/// their pages, and a page is only copied the first time it is written to, so copying memory
+/// costs a reference per page plus a copy of each page written since.
+#[derive(Clone)]
+pub(crate) struct Memory {
+    pages: Vec<Arc<Page>>,
+}
+
+impl Memory {
+    /// Creates zeroed memory, where every page is shared until written to.
+    pub fn new() -> Self {
+        let zero_page = Arc::new([0; PAGE_SIZE]);
+
+        Memory {
+            pages: (0..PAGE_COUNT).map(|_| zero_page.clone()).collect(),
+        }
+    }
+
+    /// Gets the number of addressable words.
+    pub fn len(&self) -> usize {
+        MEM_ADDR_SPACE
+    }
+
+    /// Gets the word at the given address, if in bounds.
+    pub fn get(&self, addr: usize) -> Option<&u16> {
+        self.pages
+            .get(addr / PAGE_SIZE)
+            .map(|page| &page[addr % PAGE_SIZE])
+    }
+
+    /// Sets the word at the given address, copying its page first if it is shared. Writes leaving
+    /// the word unchanged never copy the page. Out of bounds addresses are ignored.
+    pub fn set(&mut self, addr: usize, val: u16) {
+        if let Some(page) = self.pages.get_mut(addr / PAGE_SIZE) {
+            if page[addr % PAGE_SIZE] != val {
+                Arc::make_mut(page)[addr % PAGE_SIZE] = val;
+            }
+        }
+    }
+
+    /// Iterates over the address ranges of the pages which may differ from those of `other`, that
+    /// is every page the two do not share.
+    pub fn unshared_pages<'a>(
+        &'a self,
+        other: &'a Memory,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.pages
+            .iter()
+            .zip(other.pages.iter())
+            .enumerate()
+            .filter(|&(_, (a, b))| !Arc::ptr_eq(a, b))
+            .map(|(i, _)| i * PAGE_SIZE..(i + 1) * PAGE_SIZE)
+    }
+
+    /// Iterates over every word in address order.
+    pub fn iter(&self) -> impl Iterator<Item = &u16> + '_ {
+        self.pages.iter().flat_map(|page| page.iter())
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = u16;
+
+    fn index(&self, addr: usize) -> &u16 {
+        &self.pages[addr / PAGE_SIZE][addr % PAGE_SIZE]
+    }
+}
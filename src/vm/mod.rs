@@ -1,20 +1,66 @@
+pub(crate) mod budget;
+pub mod call_graph;
+pub mod coverage;
+pub(crate) mod debugger;
+mod decode;
 mod input_buffer;
+pub mod intrinsic;
+pub mod io;
+mod memory;
 pub(crate) mod op;
+pub mod profiler;
+mod scan;
+pub mod self_modify;
+pub mod snapshot;
+#[cfg(test)]
+mod tests;
+pub mod trace;
+pub mod transcript;
+mod undo;
 
 use crate::{
     error::Error,
-    vm::{input_buffer::InputBuffer, op::Op},
+    vm::{
+        budget::{Budget, Watchdog},
+        call_graph::CallGraph,
+        coverage::Coverage,
+        debugger::{Breakpoints, Debugger, Resume, WatchHit, Watchpoints},
+        decode::{DecodeCache, Instruction, Operand},
+        input_buffer::InputBuffer,
+        intrinsic::{GuestState, Intrinsic, IntrinsicAction},
+        io::{InputSource, NullOutput, OutputSink, StdinInput, StdoutOutput},
+        memory::Memory,
+        op::Op,
+        profiler::{ProfileReport, Profiler},
+        scan::MemoryScan,
+        self_modify::{SelfModification, SelfModifyDetector, SelfModifyMode, SelfModifyReport},
+        snapshot::Snapshot,
+        trace::{TraceRecord, Tracer},
+        transcript::TranscriptWriter,
+        undo::{UndoEntry, UndoLog},
+    },
     Result,
 };
 use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap, io as stdio, path::Path, rc::Rc, time::Duration};
 
 pub(crate) const MEM_ADDR_SPACE: usize = 0x8000;
 pub(crate) const FIFTEEN_BIT_MODULO: u16 = 0x8000;
 
-type Memory = [u16; MEM_ADDR_SPACE];
 type Registers = [u16; 8];
 type Stack = Vec<u16>;
 
+/// A register or memory write made by an instruction.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WriteRecord {
+    pub addr: u16,
+    pub old: u16,
+    pub new: u16,
+    /// Whether the write was made by an intrinsic the instruction called, rather than by the
+    /// instruction itself.
+    pub native: bool,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct VirtualMachineState {
     pub mem: Vec<u16>,
@@ -34,6 +80,63 @@ impl VirtualMachineState {
     }
 }
 
+/// The result of executing a single instruction with `VirtualMachine::step`.
+#[derive(Debug)]
+pub enum StepOutcome {
+    /// The instruction was executed and the VM is ready for the next one.
+    Continued,
+    /// The VM halted through `HALT`.
+    Halted,
+    /// The VM halted through `RET` with an empty stack.
+    RetOnEmptyStack,
+    /// The input source is exhausted, so `IN` could not be executed. The program counter is left
+    /// on the `IN` instruction, so it will be retried by the next step.
+    WaitingForInput,
+    /// The `exit` command was entered while waiting for input, so `IN` was not executed.
+    UserExit,
+    /// An `OUT` instruction printed the given character.
+    Output(char),
+    /// Execution stopped at a breakpoint before the instruction at the given address. The next
+    /// step will execute the instruction.
+    Breakpoint(usize),
+    /// The instruction was executed and made an access matching a watchpoint.
+    Watchpoint(WatchHit),
+    /// The instruction was executed and modified code, while self-modifying code detection is
+    /// in `SelfModifyMode::Break`.
+    SelfModified(SelfModification),
+    /// No instruction was executed, as the given budget is exhausted.
+    BudgetExhausted(Budget),
+    /// The instruction failed with the given error.
+    Error(Error),
+}
+
+/// The reason `VirtualMachine::run` stopped.
+#[derive(Debug)]
+pub enum RunOutcome {
+    /// The VM halted through `HALT`.
+    Halted,
+    /// The VM halted through `RET` with an empty stack.
+    RetOnEmptyStack,
+    /// The `exit` command was entered.
+    UserExit,
+    /// The input source ran out while the VM was waiting for input. Running the VM again once
+    /// more input is available will carry on from the `IN` instruction.
+    InputExhausted,
+    /// The given budget ran out before the VM halted.
+    BudgetExhausted(Budget),
+}
+
+/// The reason `VirtualMachine::reverse_continue` stopped stepping backwards.
+#[derive(Debug)]
+pub enum ReverseOutcome {
+    /// Execution was rewound to an instruction with an enabled breakpoint.
+    Breakpoint(usize),
+    /// Execution was rewound to an instruction which hit a watchpoint.
+    Watchpoint(WatchHit),
+    /// The undo log ran out of recorded instructions.
+    HistoryExhausted,
+}
+
 /// The Synacor Virtual Machine implementation.
 pub struct VirtualMachine {
     mem: Memory,
@@ -41,20 +144,193 @@ pub struct VirtualMachine {
     stack: Stack,
     pc: usize,
     input_buffer: InputBuffer,
+    output: Box<dyn OutputSink>,
+    debugger: Debugger,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+    call_graph: Option<CallGraph>,
+    coverage: Option<Coverage>,
+    self_modify: Option<SelfModifyDetector>,
+    undo_log: Option<UndoLog>,
+    step_writes: Vec<WriteRecord>,
+    /// The stack before an instruction which called an intrinsic, which may have changed it in
+    /// any way, kept while an undo log is recorded.
+    step_stack: Option<Stack>,
+    watchdog: Watchdog,
+    strict: bool,
+    exit_requested: bool,
+    intrinsics: HashMap<u16, Intrinsic>,
+    native_call: Option<usize>,
+    decode_cache: Option<DecodeCache>,
+    scan: Option<MemoryScan>,
+    executed: u64,
+}
+
+/// Forks the VM, copying its state, breakpoints, watchpoints, budget and strict mode, and sharing
+/// its intrinsics. The fork reads from an empty input and discards its output until given its own
+/// with `set_input` and `set_output`, and records no trace, profile, call graph, coverage or undo
+/// log.
+impl Clone for VirtualMachine {
+    fn clone(&self) -> Self {
+        VirtualMachine {
+            mem: self.mem.clone(),
+            reg: self.reg,
+            stack: self.stack.clone(),
+            pc: self.pc,
+            input_buffer: self.input_buffer.fork(),
+            output: Box::new(NullOutput),
+            debugger: self.debugger.clone(),
+            tracer: None,
+            profiler: None,
+            call_graph: None,
+            coverage: None,
+            self_modify: None,
+            undo_log: None,
+            step_writes: Vec::with_capacity(4),
+            step_stack: None,
+            watchdog: self.watchdog.clone(),
+            strict: self.strict,
+            exit_requested: false,
+            intrinsics: self.intrinsics.clone(),
+            native_call: None,
+            decode_cache: self.decode_cache.as_ref().map(|_| DecodeCache::new()),
+            scan: None,
+            executed: self.executed,
+        }
+    }
 }
 
 impl VirtualMachine {
     /// Creates a new VirtualMachine instance.
     pub fn new() -> Self {
         VirtualMachine {
-            mem: [0; MEM_ADDR_SPACE],
+            mem: Memory::new(),
             reg: [0; 8],
             stack: Vec::with_capacity(0x10000),
             pc: 0,
-            input_buffer: InputBuffer::new(),
+            input_buffer: InputBuffer::new(Box::new(StdinInput)),
+            output: Box::new(StdoutOutput),
+            debugger: Debugger::new(),
+            tracer: None,
+            profiler: None,
+            call_graph: None,
+            coverage: None,
+            self_modify: None,
+            undo_log: None,
+            step_writes: Vec::with_capacity(4),
+            step_stack: None,
+            watchdog: Watchdog::default(),
+            strict: false,
+            exit_requested: false,
+            intrinsics: HashMap::new(),
+            native_call: None,
+            decode_cache: Some(DecodeCache::new()),
+            scan: None,
+            executed: 0,
+        }
+    }
+
+    /// Sets the source the VM reads player input from. Defaults to stdin.
+    pub fn with_input<I: InputSource + 'static>(mut self, input: I) -> Self {
+        self.set_input(input);
+        self
+    }
+
+    /// Sets the sink the VM writes `OUT` characters to. Defaults to stdout.
+    pub fn with_output<O: OutputSink + 'static>(mut self, output: O) -> Self {
+        self.set_output(output);
+        self
+    }
+
+    /// Limits the VM to executing at most `instructions` more instructions.
+    pub fn with_fuel(mut self, instructions: u64) -> Self {
+        self.set_fuel(Some(instructions));
+        self
+    }
+
+    /// Limits each run of the VM to the given wall-clock time.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.set_time_limit(Some(time_limit));
+        self
+    }
+
+    /// Sets the source the VM reads player input from, keeping any partially processed line.
+    pub fn set_input<I: InputSource + 'static>(&mut self, input: I) {
+        self.input_buffer.set_input(Box::new(input));
+    }
+
+    /// Sets the sink the VM writes `OUT` characters to.
+    pub fn set_output<O: OutputSink + 'static>(&mut self, output: O) {
+        self.output = Box::new(output);
+    }
+
+    /// Enables or disables strict mode. In strict mode, literal operands above `0x8007`, `RET`
+    /// with an empty stack and output of non-ASCII values are errors instead of being accepted.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.set_strict(strict);
+        self
+    }
+
+    /// Enables or disables strict mode, see `VirtualMachine::with_strict`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Checks if the VM is in strict mode.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Sets the prefix which shell commands given at the program prompt must start with, such as
+    /// `!` in `!break 0x0aa0`, so that lines starting with the name of a command still reach the
+    /// program. The prefix is `!` by default. Without a prefix, any line starting with the name of a
+    /// command is taken as one.
+    pub fn set_command_prefix(&mut self, prefix: Option<String>) {
+        self.input_buffer.set_command_prefix(prefix);
+    }
+
+    /// Gets the prefix of shell commands given at the program prompt, if set.
+    pub fn command_prefix(&self) -> Option<&str> {
+        self.input_buffer.command_prefix()
+    }
+
+    /// Enables or disables caching of decoded instructions, which is enabled by default. Cached
+    /// instructions are dropped whenever memory they were decoded from is written to, so
+    /// self-modifying code runs the same either way.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        if !enabled {
+            self.decode_cache = None;
+        } else if self.decode_cache.is_none() {
+            self.decode_cache = Some(DecodeCache::new());
         }
     }
 
+    /// Checks if decoded instructions are cached.
+    pub fn has_decode_cache(&self) -> bool {
+        self.decode_cache.is_some()
+    }
+
+    /// Sets the number of instructions the VM may still execute, or `None` for no limit. Use this
+    /// to refuel a VM whose instruction budget is exhausted before resuming it.
+    pub fn set_fuel(&mut self, instructions: Option<u64>) {
+        self.watchdog.set_fuel(instructions);
+    }
+
+    /// Gets the number of instructions the VM may still execute, if limited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.watchdog.fuel()
+    }
+
+    /// Sets the wall-clock time limit of each run, or `None` for no limit.
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.watchdog.set_time_limit(time_limit);
+    }
+
+    /// Gets the total number of instructions executed by the VM.
+    pub fn instruction_count(&self) -> u64 {
+        self.executed
+    }
+
     /// Loads bytecode as a `&[u8]` into the virtual machine memory. Bytecode should be in little
     /// endian format.
     pub fn load_bytecode(mut self, bytecode: &[u8]) -> Result<Self> {
@@ -76,207 +352,772 @@ impl VirtualMachine {
         Ok(self)
     }
 
-    /// Runs the virtual machine starting with instruction at memory address 0x0000.
-    pub fn run(mut self) -> Result<()> {
-        use self::Op::*;
+    /// Runs the virtual machine starting with instruction at memory address 0x0000, or wherever
+    /// the last run stopped. Execution drops into the VM shell whenever a breakpoint or watchpoint
+    /// is hit. If the instruction budget or time limit runs out, the VM is left as it is, so that
+    /// it can be inspected, saved or resumed. Whatever stops the run, any trace being recorded is
+    /// flushed to its file before returning.
+    pub fn run(&mut self) -> Result<RunOutcome> {
+        self.watchdog.start();
+        let result = self.run_to_completion();
+        self.watchdog.stop();
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.flush()?;
+        }
+        result
+    }
 
+    fn run_to_completion(&mut self) -> Result<RunOutcome> {
         loop {
-            match Op::from_u16(self.read()?) {
-                HALT => return Ok(()),
-
-                SET => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let val = self.inc_pc().read()?;
-                    self.write(out_addr, val)?;
+            let stop = match self.step() {
+                StepOutcome::Continued | StepOutcome::Output(_) => {
+                    if self.debugger.should_pause(self.pc, self.stack.len()) {
+                        self.shell()?
+                    } else {
+                        None
+                    }
+                }
+                StepOutcome::Breakpoint(addr) => {
+                    self.print_line(&format!("Breakpoint hit at {:#06x}", addr))?;
+                    self.shell()?
+                }
+                StepOutcome::Watchpoint(hit) => {
+                    self.print_line(&hit.to_string())?;
+                    self.shell()?
                 }
+                StepOutcome::SelfModified(modification) => {
+                    self.print_line(&modification.to_string())?;
+                    self.shell()?
+                }
+                StepOutcome::Halted => {
+                    self.stop_trace()?;
+                    Some(RunOutcome::Halted)
+                }
+                StepOutcome::RetOnEmptyStack => {
+                    self.stop_trace()?;
+                    Some(RunOutcome::RetOnEmptyStack)
+                }
+                StepOutcome::UserExit => Some(RunOutcome::UserExit),
+                StepOutcome::WaitingForInput => Some(RunOutcome::InputExhausted),
+                StepOutcome::BudgetExhausted(budget) => Some(RunOutcome::BudgetExhausted(budget)),
+                StepOutcome::Error(e) => return Err(e),
+            };
+
+            if let Some(outcome) = stop {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    /// Steps the virtual machine until `predicate` returns true after an instruction, or until an
+    /// instruction halts, fails or waits for input, or a budget runs out. Returns the outcome of
+    /// the last step taken.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> StepOutcome
+    where
+        F: FnMut(&VirtualMachine) -> bool,
+    {
+        self.watchdog.start();
+
+        let outcome = loop {
+            let outcome = self.step();
+
+            match outcome {
+                StepOutcome::Continued | StepOutcome::Output(_) if !predicate(self) => {}
+                _ => break outcome,
+            }
+        };
+
+        self.watchdog.stop();
+        outcome
+    }
+
+    /// Executes the single instruction at the program counter, unless an enabled breakpoint is
+    /// set on it.
+    pub fn step(&mut self) -> StepOutcome {
+        if self.is_unobserved() {
+            // `IN` may first process shell commands, and an instruction which fails to decode is
+            // reported by the checked path, so that only the common case is taken here.
+            match self.decode() {
+                Ok(instruction) if instruction.op != Op::IN => return self.step_fast(instruction),
+                _ => {}
+            }
+        }
+
+        if let Some(budget) = self.watchdog.check(self.executed) {
+            return StepOutcome::BudgetExhausted(budget);
+        }
+
+        if self.debugger.check_breakpoint(self.pc) {
+            return StepOutcome::Breakpoint(self.pc);
+        }
+
+        if self.at_op(Op::IN) {
+            match self.await_input() {
+                Ok(None) => {}
+                Ok(Some(outcome)) => return outcome,
+                Err(e) => return StepOutcome::Error(e),
+            }
+        }
+
+        if !self.is_recording() {
+            return self.step_unrecorded();
+        }
 
-                PUSH => {
-                    let val = self.inc_pc().read()?;
-                    self.stack.push(val);
+        let pc = self.pc;
+        let opcode = self.mem.get(pc).cloned().unwrap_or_default();
+        let trace_record = self.begin_trace_record();
+        let undo_entry = self.begin_undo_entry();
+        self.step_writes.clear();
+        self.debugger.arm(self.pc);
+        let result = self.exec();
+        let watch_hit = self.debugger.disarm();
+        let mut modification = None;
+        let mut warned = Ok(());
+
+        if result.is_ok() {
+            self.executed += 1;
+            self.watchdog.consume();
+
+            let native_call = self.native_call.take();
+            let next_pc = native_call.unwrap_or(self.pc);
+
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.record(pc, opcode, next_pc, self.executed);
+
+                if native_call.is_some() {
+                    profiler.leave_routine(self.executed);
                 }
+            }
+            if let Some(coverage) = self.coverage.as_mut() {
+                coverage.mark_executed(pc);
+            }
+            if let Some(detector) = self.self_modify.as_mut() {
+                modification = detector.record(&self.mem, pc, &self.step_writes);
 
-                POP => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    if let Some(val) = self.stack.pop() {
-                        self.write(out_addr, val)?;
-                    } else {
-                        return Err(Error::PopFromEmptyStack { pc: self.pc });
+                if detector.mode() == SelfModifyMode::Warn {
+                    if let Some(m) = modification.take() {
+                        warned = self.output.warn(&m.to_string());
                     }
                 }
+            }
+            if let Some(call_graph) = self.call_graph.as_mut() {
+                match Op::from_u16(opcode) {
+                    Op::CALL => call_graph.record_call(pc, next_pc),
+                    Op::RET => call_graph.record_ret(),
+                    _ => {}
+                }
+                if native_call.is_some() {
+                    call_graph.record_ret();
+                }
+            }
+        }
+        let step_stack = self.step_stack.take();
+        if let (Ok(_), Some(entry)) = (&result, undo_entry) {
+            self.end_undo_entry(entry, step_stack, watch_hit);
+        }
 
-                EQ => {
-                    let addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+        let result = result.and_then(|outcome| warned.map(|_| outcome).map_err(Error::from));
+        let result = match (result, trace_record) {
+            (Ok(outcome), Some(record)) => self.end_trace_record(record).map(|_| outcome),
+            (result, _) => result,
+        };
+
+        match (result, watch_hit, modification) {
+            (Err(e), _, _) => StepOutcome::Error(e),
+            (Ok(_), Some(hit), _) => StepOutcome::Watchpoint(hit),
+            (Ok(_), None, Some(m)) => StepOutcome::SelfModified(m),
+            (Ok(outcome), None, None) => outcome,
+        }
+    }
 
-                    self.write(addr, if a == b { 1 } else { 0 })?;
-                }
+    /// Executes the decoded instruction at the program counter while neither the debugger, a
+    /// recorder nor the time limit looks at it, skipping all of their checks. Only the instruction
+    /// budget is enforced.
+    fn step_fast(&mut self, instruction: Instruction) -> StepOutcome {
+        if self.watchdog.fuel() == Some(0) {
+            return StepOutcome::BudgetExhausted(Budget::Instructions);
+        }
 
-                GT => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+        match self.exec_instruction(instruction) {
+            Err(e) => StepOutcome::Error(e),
+            Ok(outcome) => {
+                self.executed += 1;
+                self.watchdog.consume();
+                self.native_call = None;
+                outcome
+            }
+        }
+    }
 
-                    self.write(out_addr, if a > b { 1 } else { 0 })?;
+    /// Executes the instruction at the program counter while nothing records it, which saves the
+    /// bookkeeping of `step` on long runs.
+    fn step_unrecorded(&mut self) -> StepOutcome {
+        self.debugger.arm(self.pc);
+        let result = self.exec();
+        let watch_hit = self.debugger.disarm();
+
+        match result {
+            Err(e) => StepOutcome::Error(e),
+            Ok(outcome) => {
+                self.executed += 1;
+                self.watchdog.consume();
+                self.native_call = None;
+
+                match watch_hit {
+                    Some(hit) => StepOutcome::Watchpoint(hit),
+                    None => outcome,
                 }
+            }
+        }
+    }
 
-                JMP => {
-                    let addr = self.inc_pc().read()?;
-                    self.set_pc(addr);
-                    continue;
-                }
+    /// Checks if nothing needs to look at the next instruction, which is then executed by
+    /// `step_fast`.
+    fn is_unobserved(&self) -> bool {
+        self.debugger.is_idle() && !self.watchdog.is_timed() && !self.is_recording()
+    }
+
+    /// Checks if a trace, undo log or any recorder looking at every executed instruction is active.
+    fn is_recording(&self) -> bool {
+        self.tracer.is_some()
+            || self.undo_log.is_some()
+            || self.profiler.is_some()
+            || self.call_graph.is_some()
+            || self.coverage.is_some()
+            || self.self_modify.is_some()
+    }
 
-                JT => {
-                    let predicate = self.inc_pc().read()?;
-                    let addr = self.inc_pc().read()?;
+    /// Processes any shell commands given at the input prompt until a line of program input is
+    /// available. Returns the outcome to stop the step with if the input source is exhausted or the
+    /// `exit` command was entered.
+    fn await_input(&mut self) -> Result<Option<StepOutcome>> {
+        let pc = self.pc;
+        self.output.flush()?;
 
-                    if predicate != 0 {
-                        self.set_pc(addr);
-                        continue;
+        loop {
+            match self
+                .input_buffer
+                .process_input(&mut *self.output, self.executed)
+            {
+                Ok(Some((cmd, args))) => {
+                    cmd.run(args, self)?;
+                    self.input_buffer.standby();
+
+                    if self.exit_requested {
+                        self.exit_requested = false;
+                        return Ok(Some(StepOutcome::UserExit));
                     }
                 }
+                Ok(None) => return Ok(None),
+                Err(ref e) if e.kind() == stdio::ErrorKind::UnexpectedEof => {
+                    return Ok(Some(StepOutcome::WaitingForInput))
+                }
+                Err(_) => return Err(Error::ReadInputErr { pc }),
+            }
+        }
+    }
 
-                JF => {
-                    let predicate = self.inc_pc().read()?;
-                    let addr = self.inc_pc().read()?;
+    /// Executes the instruction at the program counter and advances the program counter past it,
+    /// unless the instruction jumped elsewhere.
+    fn exec(&mut self) -> Result<StepOutcome> {
+        let instruction = self.decode()?;
+        self.exec_instruction(instruction)
+    }
 
-                    if predicate == 0 {
-                        self.set_pc(addr);
-                        continue;
-                    }
-                }
+    /// Executes the given instruction, decoded from the program counter.
+    fn exec_instruction(&mut self, instruction: Instruction) -> Result<StepOutcome> {
+        use self::Op::*;
 
-                ADD => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+        let pc = self.pc;
+        let Instruction { op, args } = instruction;
+        let [a, b, c] = args;
 
-                    self.write(out_addr, a.wrapping_add(b) % FIFTEEN_BIT_MODULO)?;
-                }
+        // The program counter rests on the last operand until the instruction is done, so that
+        // errors and return addresses refer to the same words as when they were read one by one.
+        self.pc = pc + op.arg_count();
 
-                MULT => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+        let outcome = match op {
+            HALT => {
+                self.output.flush()?;
+                return Ok(StepOutcome::Halted);
+            }
 
-                    self.write(out_addr, a.wrapping_mul(b) % FIFTEEN_BIT_MODULO)?;
-                }
+            SET => {
+                let val = self.operand(pc + 2, b)?;
+                self.write(a.to_u16(), val)?;
+                StepOutcome::Continued
+            }
 
-                MOD => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+            PUSH => {
+                let val = self.operand(pc + 1, a)?;
+                self.stack.push(val);
+                StepOutcome::Continued
+            }
 
-                    self.write(out_addr, a % b)?;
+            POP => {
+                if let Some(val) = self.stack.pop() {
+                    self.write(a.to_u16(), val)?;
+                } else {
+                    return Err(Error::PopFromEmptyStack { pc: self.pc });
                 }
+                StepOutcome::Continued
+            }
 
-                AND => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+            EQ => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
 
-                    self.write(out_addr, a & b)?;
-                }
+                self.write(a.to_u16(), if x == y { 1 } else { 0 })?;
+                StepOutcome::Continued
+            }
 
-                OR => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
-                    let b = self.inc_pc().read()?;
+            GT => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
 
-                    self.write(out_addr, a | b)?;
-                }
+                self.write(a.to_u16(), if x > y { 1 } else { 0 })?;
+                StepOutcome::Continued
+            }
 
-                NOT => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let a = self.inc_pc().read()?;
+            JMP => {
+                let addr = self.operand(pc + 1, a)?;
+                self.set_pc(addr);
+                return Ok(StepOutcome::Continued);
+            }
+
+            JT => {
+                let predicate = self.operand(pc + 1, a)?;
+                let addr = self.operand(pc + 2, b)?;
 
-                    self.write(out_addr, !a % FIFTEEN_BIT_MODULO)?;
+                if predicate != 0 {
+                    self.set_pc(addr);
+                    return Ok(StepOutcome::Continued);
                 }
+                StepOutcome::Continued
+            }
 
-                RMEM => {
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let val_addr = self.inc_pc().read()?;
-                    let val = self.read_from_addr(val_addr)?;
+            JF => {
+                let predicate = self.operand(pc + 1, a)?;
+                let addr = self.operand(pc + 2, b)?;
 
-                    self.write(out_addr, val)?;
+                if predicate == 0 {
+                    self.set_pc(addr);
+                    return Ok(StepOutcome::Continued);
                 }
+                StepOutcome::Continued
+            }
+
+            ADD => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
+
+                self.write(a.to_u16(), x.wrapping_add(y) % FIFTEEN_BIT_MODULO)?;
+                StepOutcome::Continued
+            }
+
+            MULT => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
+
+                self.write(a.to_u16(), x.wrapping_mul(y) % FIFTEEN_BIT_MODULO)?;
+                StepOutcome::Continued
+            }
 
-                WMEM => {
-                    let out_addr = self.inc_pc().read()?;
-                    let val = self.inc_pc().read()?;
+            MOD => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
 
-                    self.write(out_addr, val)?;
+                if y == 0 {
+                    return Err(Error::DivisionByZero { pc });
                 }
+                self.write(a.to_u16(), x % y)?;
+                StepOutcome::Continued
+            }
+
+            AND => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
+
+                self.write(a.to_u16(), x & y)?;
+                StepOutcome::Continued
+            }
+
+            OR => {
+                let x = self.operand(pc + 2, b)?;
+                let y = self.operand(pc + 3, c)?;
+
+                self.write(a.to_u16(), x | y)?;
+                StepOutcome::Continued
+            }
+
+            NOT => {
+                let x = self.operand(pc + 2, b)?;
+
+                self.write(a.to_u16(), !x % FIFTEEN_BIT_MODULO)?;
+                StepOutcome::Continued
+            }
 
-                CALL => {
-                    let jmp_addr = self.inc_pc().read()?;
-                    self.stack.push(self.pc as u16 + 1);
+            RMEM => {
+                let val_addr = self.operand(pc + 2, b)?;
+                let val = self.read_from_addr(val_addr)?;
 
+                self.write(a.to_u16(), val)?;
+                StepOutcome::Continued
+            }
+
+            WMEM => {
+                let out_addr = self.operand(pc + 1, a)?;
+                let val = self.operand(pc + 2, b)?;
+
+                self.write(out_addr, val)?;
+                StepOutcome::Continued
+            }
+
+            CALL => {
+                let jmp_addr = self.operand(pc + 1, a)?;
+                self.stack.push(self.pc as u16 + 1);
+
+                if self.call_intrinsic(pc, jmp_addr) == IntrinsicAction::Return {
+                    self.native_call = Some(jmp_addr as usize);
+                    let ret_addr = self.stack.pop().unwrap_or_default();
+                    self.set_pc(ret_addr);
+                } else {
                     self.set_pc(jmp_addr);
-                    continue;
                 }
+                return Ok(StepOutcome::Continued);
+            }
 
-                RET => {
-                    if let Some(addr) = self.stack.pop() {
-                        self.set_pc(addr);
-                        continue;
-                    } else {
-                        // Halt if stack empty
-                        return Ok(());
-                    }
+            RET => {
+                if let Some(addr) = self.stack.pop() {
+                    self.set_pc(addr);
+                    return Ok(StepOutcome::Continued);
+                } else if self.strict {
+                    return Err(Error::StackUnderflowOnRet { pc });
+                } else {
+                    // Halt if stack empty
+                    self.output.flush()?;
+                    return Ok(StepOutcome::RetOnEmptyStack);
                 }
+            }
 
-                OUT => print!("{}", self.inc_pc().read_char()?),
-
-                IN => {
-                    let pc = self.pc;
-                    while let Some((cmd, args)) = self
-                        .input_buffer
-                        .process_input()
-                        .map_err(|_| Error::ReadInputErr { pc })?
-                    {
-                        cmd.run(args, &mut self)?;
-                        self.input_buffer.standby();
-                    }
-
-                    let out_addr = self.inc_pc().read_mem()?;
-                    let c = self.input_buffer.read_byte();
+            OUT => {
+                let val = self.operand(pc + 1, a)?;
 
-                    if c == b'\n' {
-                        self.input_buffer.standby();
-                    }
-                    self.write(out_addr, c as u16)?;
+                if self.strict && val > 0x7f {
+                    return Err(Error::NonAsciiOutput { pc, value: val });
                 }
+                let c = val as u8 as char;
+                self.output.write_char(c)?;
+                self.input_buffer.observe_output(c);
+                StepOutcome::Output(c)
+            }
 
-                NOOP => {}
+            IN => {
+                let c = self.input_buffer.read_byte();
 
-                RegOrData(operation) => {
-                    return Err(Error::InvalidOperation {
-                        pc: self.pc,
-                        operation,
-                    })
+                if c == b'\n' {
+                    self.input_buffer.standby();
                 }
+                self.write(a.to_u16(), c as u16)?;
+                StepOutcome::Continued
             }
 
-            self.inc_pc();
+            NOOP => StepOutcome::Continued,
+
+            RegOrData(operation) => {
+                return Err(Error::InvalidOperation {
+                    pc: self.pc,
+                    operation,
+                })
+            }
+        };
+
+        self.inc_pc();
+        Ok(outcome)
+    }
+
+    /// Gets the set of breakpoints.
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.debugger.breakpoints
+    }
+
+    /// Gets the set of breakpoints for modification.
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.debugger.breakpoints
+    }
+
+    /// Gets the set of watchpoints.
+    pub fn watchpoints(&self) -> &Watchpoints {
+        &self.debugger.watchpoints
+    }
+
+    /// Gets the set of watchpoints for modification.
+    pub fn watchpoints_mut(&mut self) -> &mut Watchpoints {
+        &mut self.debugger.watchpoints
+    }
+
+    /// Starts recording every executed instruction to a trace file at the given path, replacing
+    /// any trace already being recorded.
+    pub fn trace_to<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.stop_trace()?;
+        self.tracer = Some(Tracer::create(path)?);
+        Ok(())
+    }
+
+    /// Stops recording the execution trace, if one is being recorded.
+    pub fn stop_trace(&mut self) -> Result<()> {
+        if let Some(mut tracer) = self.tracer.take() {
+            tracer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Checks if an execution trace is being recorded.
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    /// Starts recording every line of input, along with the instruction count it was read at, to
+    /// a transcript file which can be replayed with `transcript::TranscriptInput`. Any previous transcript
+    /// is closed.
+    pub fn record_transcript<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.input_buffer
+            .set_transcript(Some(TranscriptWriter::create(path)?));
+        Ok(())
+    }
+
+    /// Stops recording the transcript, if one is being recorded.
+    pub fn stop_transcript(&mut self) {
+        self.input_buffer.set_transcript(None);
+    }
+
+    /// Checks if a transcript is being recorded.
+    pub fn is_recording_transcript(&self) -> bool {
+        self.input_buffer.has_transcript()
+    }
+
+    /// Starts profiling executed instructions, discarding any previous profile.
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// Stops profiling and discards the profile.
+    pub fn stop_profiling(&mut self) {
+        self.profiler = None;
+    }
+
+    /// Checks if executed instructions are being profiled.
+    pub fn is_profiling(&self) -> bool {
+        self.profiler.is_some()
+    }
+
+    /// Builds a report of the executions profiled so far, if profiling.
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        self.profiler.as_ref().map(|profiler| profiler.report(self))
+    }
+
+    /// Registers a native function to run whenever `CALL` targets the given address, replacing any
+    /// intrinsic already registered there. The function sees the state of the VM with the return
+    /// address pushed, and either returns to the caller or falls back to the guest subroutine.
+    pub fn register_intrinsic<F>(&mut self, addr: u16, intrinsic: F)
+    where
+        F: FnMut(&mut GuestState<'_>) -> IntrinsicAction + 'static,
+    {
+        self.intrinsics
+            .insert(addr, Rc::new(RefCell::new(intrinsic)));
+    }
+
+    /// Removes the intrinsic registered at the given address. Returns false if there was none.
+    pub fn remove_intrinsic(&mut self, addr: u16) -> bool {
+        self.intrinsics.remove(&addr).is_some()
+    }
+
+    /// Checks if an intrinsic is registered at the given address.
+    pub fn has_intrinsic(&self, addr: u16) -> bool {
+        self.intrinsics.contains_key(&addr)
+    }
+
+    /// Captures the memory, registers, stack, program counter, instruction count and input buffer
+    /// of the VM.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            mem: self.mem.clone(),
+            reg: self.reg,
+            stack: self.stack.clone(),
+            pc: self.pc,
+            input: self.input_buffer.snapshot(),
+            executed: self.executed,
+        }
+    }
+
+    /// Puts the VM back into the state captured by `snapshot`. The input source and output sink
+    /// are left as they are, and the undo log is cleared as its history no longer applies, as are
+    /// the subroutines the profiler saw being entered.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        if let Some(cache) = self.decode_cache.as_mut() {
+            for pages in self.mem.unshared_pages(&snapshot.mem) {
+                cache.invalidate_range(pages);
+            }
+        }
+        self.mem = snapshot.mem.clone();
+        self.reg = snapshot.reg;
+        self.stack.clone_from(&snapshot.stack);
+        self.pc = snapshot.pc;
+        self.input_buffer.restore_snapshot(snapshot.input.clone());
+        self.executed = snapshot.executed;
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.clear_frames();
+        }
+        if let Some(capacity) = self.undo_capacity() {
+            self.record_undo_log(capacity);
         }
     }
 
-    /// Converts the VM's current memory layout into assembly.
-    pub fn disassemble(&self, include_addresses: bool) -> String {
+    /// Starts detecting instructions which modify code, discarding any previous report.
+    pub fn start_self_modify_detection(&mut self, mode: SelfModifyMode) {
+        self.self_modify = Some(SelfModifyDetector::new(mode));
+    }
+
+    /// Stops detecting self-modifying code and discards the report.
+    pub fn stop_self_modify_detection(&mut self) {
+        self.self_modify = None;
+    }
+
+    /// Gets how self-modifying code is being reacted to, if it is being detected.
+    pub fn self_modify_mode(&self) -> Option<SelfModifyMode> {
+        self.self_modify.as_ref().map(|x| x.mode())
+    }
+
+    /// Gets the report of every region of code modified so far, if detecting self-modifying code.
+    pub fn self_modify_report(&self) -> Option<SelfModifyReport> {
+        self.self_modify.as_ref().map(|x| x.report())
+    }
+
+    /// Starts recording code coverage, discarding any previous coverage map.
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    /// Stops recording code coverage and discards the coverage map.
+    pub fn stop_coverage(&mut self) {
+        self.coverage = None;
+    }
+
+    /// Gets the coverage map recorded so far, if recording.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Starts recording a call graph, discarding any previous one.
+    pub fn start_call_graph(&mut self) {
+        self.call_graph = Some(CallGraph::new());
+    }
+
+    /// Stops recording the call graph and discards it.
+    pub fn stop_call_graph(&mut self) {
+        self.call_graph = None;
+    }
+
+    /// Gets the call graph recorded so far, if recording.
+    pub fn call_graph(&self) -> Option<&CallGraph> {
+        self.call_graph.as_ref()
+    }
+
+    /// Starts recording an undo log of the last `capacity` executed instructions, so that they can
+    /// be stepped back over. Any existing undo log is discarded.
+    pub fn record_undo_log(&mut self, capacity: usize) {
+        self.undo_log = Some(UndoLog::new(capacity));
+    }
+
+    /// Stops recording the undo log and discards it.
+    pub fn stop_undo_log(&mut self) {
+        self.undo_log = None;
+    }
+
+    /// The number of executed instructions which can currently be stepped back over.
+    pub fn undo_history_len(&self) -> usize {
+        self.undo_log.as_ref().map_or(0, |log| log.len())
+    }
+
+    /// The maximum number of instructions kept in the undo log, if one is being recorded.
+    pub fn undo_capacity(&self) -> Option<usize> {
+        self.undo_log.as_ref().map(|log| log.capacity())
+    }
+
+    /// Reverts the most recently executed instruction recorded in the undo log. Returns false if
+    /// there is no instruction to step back over.
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self.undo_log.as_mut().and_then(|log| log.pop()) {
+            Some(x) => x,
+            None => return false,
+        };
+
+        for write in entry.writes.iter().rev() {
+            if Self::is_reg(write.addr) {
+                self.reg[write.addr as usize - MEM_ADDR_SPACE] = write.old;
+            } else {
+                self.set_mem(write.addr as usize, write.old);
+            }
+        }
+
+        self.stack.truncate(entry.stack_len - entry.popped.len());
+        self.stack.extend(entry.popped);
+
+        if let Some(cursor) = entry.input {
+            self.input_buffer.restore(cursor);
+        }
+
+        self.pc = entry.pc;
+        self.debugger.ignore_breakpoint_at(self.pc);
+        true
+    }
+
+    /// Steps backwards until reaching an instruction with an enabled breakpoint, or one which hit
+    /// a watchpoint when it was executed.
+    pub fn reverse_continue(&mut self) -> ReverseOutcome {
+        loop {
+            let watch_hit = match self.undo_log.as_ref().and_then(|log| log.peek()) {
+                Some(entry) => entry.watch_hit,
+                None => return ReverseOutcome::HistoryExhausted,
+            };
+
+            self.step_back();
+            if let Some(hit) = watch_hit {
+                return ReverseOutcome::Watchpoint(hit);
+            }
+            if self.debugger.breakpoints.is_enabled(self.pc) {
+                return ReverseOutcome::Breakpoint(self.pc);
+            }
+        }
+    }
+
+    /// Converts the VM's current memory layout into assembly. If `show_coverage` is set, every
+    /// line is prefixed with its coverage marker, see `Coverage`; addresses are considered never
+    /// executed if coverage is not being recorded.
+    pub fn disassemble(&self, include_addresses: bool, show_coverage: bool) -> String {
         let mut asm = String::new();
         let mut remaining_args = 0;
+        let empty_coverage = Coverage::new();
+        let coverage = self.coverage.as_ref().unwrap_or(&empty_coverage);
 
         for (i, &x) in self.mem.iter().enumerate() {
             let op = Op::from_u16(x);
+            let is_instruction = Op::is_op(x) && remaining_args == 0;
             let address = if include_addresses && remaining_args == 0 {
                 format!("{:#06x}:\t", i)
             } else {
                 "".into()
             };
+            let marker = if show_coverage && remaining_args == 0 {
+                format!("{} ", coverage.marker(i, is_instruction))
+            } else {
+                "".into()
+            };
 
-            if Op::is_op(x) && remaining_args == 0 {
-                asm.push_str(&format!("\n{}{}", address, op));
+            if is_instruction {
+                asm.push_str(&format!("\n{}{}{}", marker, address, op));
                 remaining_args = op.arg_count();
 
                 continue;
@@ -285,22 +1126,263 @@ impl VirtualMachine {
             let delimeter = if remaining_args == 0 { '\n' } else { '\t' };
 
             if Self::is_reg(x) {
-                asm.push_str(&format!("{}{}", delimeter, op));
+                asm.push_str(&format!("{}{}{}", delimeter, marker, op));
             } else {
-                asm.push_str(&format!("{}{}{:04x}", delimeter, address, x));
+                asm.push_str(&format!("{}{}{}{:04x}", delimeter, marker, address, x));
             }
             remaining_args = remaining_args.checked_sub(1).unwrap_or(0);
         }
         asm
     }
 
+    /// Disassembles the single instruction at the given address.
+    pub(crate) fn disassemble_instruction(&self, addr: usize) -> String {
+        let op = match self.mem.get(addr) {
+            Some(&x) => Op::from_u16(x),
+            None => return String::new(),
+        };
+        let args = self
+            .mem
+            .iter()
+            .skip(addr + 1)
+            .take(op.arg_count())
+            .map(|&x| Op::RegOrData(x).to_string())
+            .collect::<Vec<_>>();
+
+        format!("{}\t{}", op, args.join("\t"))
+    }
+
+    //
+    // Debugger Helpers
+    // ----------------
+    /// Pauses execution and processes shell commands until one of them resumes execution. Returns
+    /// the outcome to stop the run with if the input source is exhausted or the `exit` command was
+    /// entered.
+    fn shell(&mut self) -> Result<Option<RunOutcome>> {
+        let pc = self.pc;
+
+        self.debugger.paused = true;
+        self.debugger.resume = Resume::Continue;
+        self.print_line(&format!(
+            "{:#06x}:\t{}",
+            pc,
+            self.disassemble_instruction(pc)
+        ))?;
+
+        while self.debugger.paused {
+            match self
+                .input_buffer
+                .read_command(&mut *self.output, self.executed)
+            {
+                Ok(Some((cmd, args))) => cmd.run(args, self)?,
+                Ok(None) => self
+                    .print_line("The VM is paused. Use `continue`, `step` or `next` to resume.")?,
+                Err(ref e) if e.kind() == stdio::ErrorKind::UnexpectedEof => {
+                    self.debugger.paused = false;
+                    return Ok(Some(RunOutcome::InputExhausted));
+                }
+                Err(_) => return Err(Error::ReadInputErr { pc }),
+            }
+        }
+
+        if self.exit_requested {
+            self.exit_requested = false;
+            return Ok(Some(RunOutcome::UserExit));
+        }
+        Ok(None)
+    }
+
+    /// Writes a message from the VM, rather than from the program, as a line to the output sink.
+    pub(crate) fn print_line(&mut self, line: &str) -> Result<()> {
+        self.output.write_str(line)?;
+        self.output.write_char('\n')?;
+        Ok(())
+    }
+
+    /// Asks the VM to stop running once the current command is done, as if by the `exit` command.
+    pub(crate) fn request_exit(&mut self) {
+        self.exit_requested = true;
+        self.debugger.paused = false;
+    }
+
+    /// Resumes execution from the shell in the given mode. Returns false if the VM is not paused.
+    pub(crate) fn resume(&mut self, resume: Resume) -> bool {
+        if !self.debugger.paused {
+            return false;
+        }
+
+        self.debugger.paused = false;
+        self.debugger.resume = resume;
+        true
+    }
+
+    /// Runs the intrinsic registered at `addr` for the `CALL` at `call_site`, if there is one.
+    fn call_intrinsic(&mut self, call_site: usize, addr: u16) -> IntrinsicAction {
+        let intrinsic = match self.intrinsics.get(&addr) {
+            Some(x) => x.clone(),
+            None => return IntrinsicAction::Fallback,
+        };
+        if self.undo_log.is_some() {
+            let mut stack = self.stack.clone();
+            stack.pop();
+            self.step_stack = Some(stack);
+        }
+        let first_write = self.step_writes.len();
+        let mut guest = GuestState {
+            vm: self,
+            call_site,
+        };
+
+        let action = (intrinsic.borrow_mut())(&mut guest);
+        for write in &mut self.step_writes[first_write..] {
+            write.native = true;
+        }
+        action
+    }
+
+    /// Checks if the instruction at the program counter is the given operation. Outside of strict
+    /// mode, an opcode held in a register is resolved, just as when the instruction is executed.
+    pub(crate) fn at_op(&self, op: Op) -> bool {
+        let opcode = match self.mem.get(self.pc) {
+            Some(&x) if !self.strict && Self::is_reg(x) => {
+                self.reg[(x - FIFTEEN_BIT_MODULO) as usize]
+            }
+            Some(&x) => x,
+            None => return false,
+        };
+
+        Op::from_u16(opcode) == op
+    }
+
+    //
+    // Trace Helpers
+    // -------------
+    /// Captures the instruction at the program counter and its register operands, if an execution
+    /// trace is being recorded.
+    fn begin_trace_record(&self) -> Option<TraceRecord> {
+        self.tracer.as_ref()?;
+
+        let opcode = *self.mem.get(self.pc)?;
+        let args = self
+            .mem
+            .iter()
+            .skip(self.pc + 1)
+            .take(Op::from_u16(opcode).arg_count())
+            .cloned()
+            .collect::<Vec<_>>();
+        let reg_values = args
+            .iter()
+            .filter_map(|&x| self.get_reg_idx(x).ok())
+            .map(|i| self.reg[i])
+            .collect();
+
+        Some(TraceRecord {
+            pc: self.pc as u16,
+            opcode,
+            args,
+            reg_values,
+            writes: vec![],
+        })
+    }
+
+    /// Completes a trace record with the writes made by the instruction, and records it.
+    fn end_trace_record(&mut self, mut record: TraceRecord) -> Result<()> {
+        record.writes = self.step_writes.iter().map(|w| (w.addr, w.new)).collect();
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.record(&record)?;
+        }
+        Ok(())
+    }
+
+    //
+    // Undo Log Helpers
+    // ----------------
+    /// Captures the state an instruction may change before it is executed, if an undo log is being
+    /// recorded.
+    fn begin_undo_entry(&self) -> Option<UndoEntry> {
+        self.undo_log.as_ref()?;
+
+        Some(UndoEntry {
+            pc: self.pc,
+            writes: vec![],
+            stack_len: self.stack.len(),
+            popped: self.stack.last().cloned().into_iter().collect(),
+            input: if self.at_op(Op::IN) {
+                Some(self.input_buffer.cursor())
+            } else {
+                None
+            },
+            watch_hit: None,
+        })
+    }
+
+    /// Completes an undo entry with the changes made by the executed instruction, and records it.
+    /// `step_stack` is the stack before the instruction, if it called an intrinsic.
+    fn end_undo_entry(
+        &mut self,
+        mut entry: UndoEntry,
+        step_stack: Option<Stack>,
+        watch_hit: Option<WatchHit>,
+    ) {
+        entry.writes = self.step_writes.clone();
+        entry.watch_hit = watch_hit;
+        if let Some(stack) = step_stack {
+            entry.popped = self.popped_since(&stack);
+        } else if self.stack.len() >= entry.stack_len {
+            entry.popped.clear();
+        }
+
+        if let Some(log) = self.undo_log.as_mut() {
+            log.push(entry);
+        }
+    }
+
+    /// Makes a change to the VM from outside of the program, such as from the shell, recording it
+    /// in the undo log so that stepping back reverts it like an instruction.
+    pub(crate) fn edit<T, F>(&mut self, f: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let entry = self.begin_undo_entry();
+        let stack = entry.as_ref().map(|_| self.stack.clone());
+        self.step_writes.clear();
+
+        let result = f(self);
+
+        if let (Some(mut entry), Some(stack)) = (entry, stack) {
+            entry.writes = self.step_writes.split_off(0);
+            entry.popped = self.popped_since(&stack);
+            entry.input = None;
+            if let Some(log) = self.undo_log.as_mut() {
+                log.push(entry);
+            }
+        }
+        result
+    }
+
+    /// Gets the values of the given earlier stack which are no longer on the stack, from the
+    /// bottom up, as the stack is put back by stepping backwards.
+    fn popped_since(&self, stack: &[u16]) -> Vec<u16> {
+        let kept = stack
+            .iter()
+            .zip(self.stack.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        stack[kept..].to_vec()
+    }
+
     //
     // VirtualMachine Runtime Helpers
     // ------------------------------
     /// Loads memory into the VM from a &[u16].
     pub(crate) fn load_mem(&mut self, memory: &[u16]) {
         for i in 0..self.mem.len() {
-            self.mem[i] = *memory.get(i).unwrap_or(&0);
+            self.mem.set(i, *memory.get(i).unwrap_or(&0));
+        }
+        if let Some(cache) = self.decode_cache.as_mut() {
+            cache.clear();
         }
     }
 
@@ -324,45 +1406,107 @@ impl VirtualMachine {
     }
 
     /// Gets the program counter.
-    pub(crate) fn pc(&self) -> usize {
+    pub fn pc(&self) -> usize {
         self.pc
     }
 
     /// Increments the program counter.
-    fn inc_pc(&mut self) -> &Self {
+    fn inc_pc(&mut self) -> &mut Self {
         self.pc += 1;
         self
     }
 
-    /// Reads value from memory at the current program counter address. If raw value in memory is a
-    /// reference to a register, it will read the value contained in that register instead.
-    fn read(&self) -> Result<u16> {
-        self.validate_access(self.pc)?;
+    /// Decodes the instruction at the program counter, taking it from the decode cache if it was
+    /// decoded before. Outside of strict mode, a register reference in place of the opcode stands
+    /// for the opcode held by the register.
+    fn decode(&mut self) -> Result<Instruction> {
+        let pc = self.pc;
+        if let Some(instruction) = self.decode_cache.as_ref().and_then(|cache| cache.get(pc)) {
+            return Ok(instruction);
+        }
+        self.decode_uncached()
+    }
 
-        let reg_or_val = self.mem[self.pc];
+    /// Decodes the instruction at the program counter from memory, caching it for next time. Kept
+    /// out of line, as allocating a page of the cache needs a large stack frame, which would
+    /// otherwise be set up on every cache hit.
+    #[inline(never)]
+    fn decode_uncached(&mut self) -> Result<Instruction> {
+        let pc = self.pc;
+        self.validate_access(pc)?;
+        let opcode = self.mem[pc];
+        let op = if !self.strict && Self::is_reg(opcode) {
+            Op::from_u16(self.read_reg(opcode)?)
+        } else {
+            Op::from_u16(opcode)
+        };
+
+        let mut args = [Operand::Lit(0); 3];
+        for (i, arg) in args.iter_mut().take(op.arg_count()).enumerate() {
+            let addr = pc + 1 + i;
+            if addr >= MEM_ADDR_SPACE {
+                return Err(Error::MemOutOfBoundsAccess { pc: addr });
+            }
+            *arg = Operand::from_u16(self.mem[addr]);
+        }
 
-        if Self::is_reg(reg_or_val) {
-            return self.read_reg(reg_or_val);
+        let instruction = Instruction { op, args };
+        if let (Some(cache), false) = (self.decode_cache.as_mut(), Self::is_reg(opcode)) {
+            cache.insert(pc, instruction);
         }
+        Ok(instruction)
+    }
+
+    /// Gets the value of an operand read from the given address. If the operand is a reference to
+    /// a register, it will read the value contained in that register instead.
+    fn operand(&mut self, addr: usize, operand: Operand) -> Result<u16> {
+        match operand {
+            Operand::Lit(val) => Ok(val),
+            Operand::Reg(idx) => {
+                let val = self.reg[idx as usize];
 
-        Ok(reg_or_val)
+                self.debugger.check_read(operand.to_u16(), val);
+                Ok(val)
+            }
+            Operand::Invalid(value) if self.strict => {
+                Err(Error::InvalidLiteral { pc: addr, value })
+            }
+            Operand::Invalid(value) => Ok(value),
+        }
     }
 
     /// Reads value from memory at the give address. If the address is a reference to a register,
     /// it will read the value contained in that register instead.
-    fn read_from_addr(&self, addr: u16) -> Result<u16> {
+    pub(crate) fn read_from_addr(&mut self, addr: u16) -> Result<u16> {
         if Self::is_reg(addr) {
             return self.read_reg(addr);
         }
 
         self.validate_access(addr)?;
 
-        Ok(self.mem[addr as usize])
+        let val = self.mem[addr as usize];
+        self.debugger.check_read(addr, val);
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.mark_read(addr as usize);
+        }
+        Ok(val)
     }
 
-    /// Just like `VirtualMachine::read`, but will return the value as a `char` instad of `u16`.
-    fn read_char(&self) -> Result<char> {
-        Ok(self.read()? as u8 as char)
+    /// Gets the word at the given memory address for inspection, without marking coverage or
+    /// checking watchpoints as reads made by instructions do. Returns `None` if the address is out
+    /// of bounds.
+    pub(crate) fn peek_mem(&self, addr: usize) -> Option<u16> {
+        self.mem.get(addr).cloned()
+    }
+
+    /// Writes the given value to the given memory address from the shell, without marking coverage
+    /// as writes made by instructions do. Made within `edit`, the write can be stepped back over.
+    pub(crate) fn poke_mem(&mut self, addr: usize, val: u16) -> Result<()> {
+        self.validate_access(addr)?;
+
+        self.record_write(addr as u16, self.mem[addr], val);
+        self.set_mem(addr, val);
+        Ok(())
     }
 
     /// Writes given value to memory at given address. If the given address is a reference to a
@@ -374,31 +1518,52 @@ impl VirtualMachine {
 
         self.validate_access(addr)?;
 
-        self.mem[addr as usize] = val;
+        self.record_write(addr, self.mem[addr as usize], val);
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.mark_written(addr as usize);
+        }
+        self.set_mem(addr as usize, val);
         Ok(())
     }
 
     //
     // Memory Access Helpers
     // ---------------------
-    /// Reads raw value from memory at the current program counter address.
-    fn read_mem(&self) -> Result<u16> {
-        self.validate_access(self.pc)?;
-
-        Ok(self.mem[self.pc])
-    }
-
     /// Writes the given value to the given memory address.
-    fn write_mem<A>(&mut self, addr: A, val: u16) -> Result<()>
+    pub(crate) fn write_mem<A>(&mut self, addr: A, val: u16) -> Result<()>
     where
         A: Into<usize> + Copy,
     {
         self.validate_access(addr)?;
 
-        self.mem[addr.into()] = val;
+        self.set_mem(addr.into(), val);
         Ok(())
     }
 
+    /// Sets the word at the given memory address, dropping any cached instruction overlapping it.
+    fn set_mem(&mut self, addr: usize, val: u16) {
+        if let (Some(cache), Some(&old)) = (self.decode_cache.as_mut(), self.mem.get(addr)) {
+            if old != val {
+                cache.invalidate(addr);
+            }
+        }
+        self.mem.set(addr, val);
+    }
+
+    /// Records a register or memory write made by the current instruction, if a trace, the undo
+    /// log or self-modifying code detection will look at it.
+    fn record_write(&mut self, addr: u16, old: u16, new: u16) {
+        self.debugger.check_write(addr, old, new);
+        if self.tracer.is_some() || self.undo_log.is_some() || self.self_modify.is_some() {
+            self.step_writes.push(WriteRecord {
+                addr,
+                old,
+                new,
+                native: false,
+            });
+        }
+    }
+
     /// Checks for validity of memory access.
     fn validate_access<A: Into<usize>>(&self, addr: A) -> Result<()> {
         if addr.into() >= FIFTEEN_BIT_MODULO.into() {
@@ -411,13 +1576,19 @@ impl VirtualMachine {
     // Register Access Helpers
     // -----------------------
     /// Reads raw value from register.
-    pub(crate) fn read_reg(&self, register: u16) -> Result<u16> {
-        Ok(self.reg[self.get_reg_idx(register)?])
+    pub(crate) fn read_reg(&mut self, register: u16) -> Result<u16> {
+        let val = self.reg[self.get_reg_idx(register)?];
+
+        self.debugger.check_read(register, val);
+        Ok(val)
     }
 
     /// Writes value to register.
     pub(crate) fn write_reg(&mut self, register: u16, val: u16) -> Result<()> {
-        self.reg[self.get_reg_idx(register)?] = val;
+        let reg_idx = self.get_reg_idx(register)?;
+
+        self.record_write(register, self.reg[reg_idx], val);
+        self.reg[reg_idx] = val;
         Ok(())
     }
 
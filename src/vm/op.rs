@@ -5,6 +5,7 @@ pub const TOTAL_OP_COUNT: u16 = 22;
 
 /// The set of opcodes available to the virtual machine processor.
 #[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Op {
     HALT,
     SET,
@@ -0,0 +1,262 @@
+use crate::{
+    constants::*,
+    vm::op::{Op, TOTAL_OP_COUNT},
+    VirtualMachine,
+};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::Write,
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// A subroutine entered with `CALL` which has not returned yet.
+struct Frame {
+    addr: u16,
+    entered_at: Instant,
+    start_count: u64,
+    child_count: u64,
+}
+
+/// Accumulated costs of every completed call to a subroutine.
+#[derive(Default)]
+struct RoutineStats {
+    calls: u64,
+    inclusive_count: u64,
+    exclusive_count: u64,
+    inclusive_time: Duration,
+}
+
+/// Counts executions per address and per opcode, and the cost of each subroutine. Subroutine
+/// boundaries are taken from `CALL` and `RET` instructions.
+pub(crate) struct Profiler {
+    addr_counts: Vec<u64>,
+    op_counts: Vec<u64>,
+    routines: HashMap<u16, RoutineStats>,
+    frames: Vec<Frame>,
+    total: u64,
+}
+
+impl Profiler {
+    /// Creates a new Profiler with no recorded executions.
+    pub fn new() -> Self {
+        Profiler {
+            addr_counts: vec![0; MEM_ADDR_SPACE],
+            op_counts: vec![0; TOTAL_OP_COUNT as usize],
+            routines: HashMap::new(),
+            frames: vec![],
+            total: 0,
+        }
+    }
+
+    /// Records an executed instruction. `pc` is the address of the instruction, `next_pc` the
+    /// program counter after it was executed, and `count` the total instructions executed so far.
+    pub fn record(&mut self, pc: usize, opcode: u16, next_pc: usize, count: u64) {
+        self.total += 1;
+        if let Some(x) = self.addr_counts.get_mut(pc) {
+            *x += 1;
+        }
+        if let Some(x) = self.op_counts.get_mut(opcode as usize) {
+            *x += 1;
+        }
+
+        match Op::from_u16(opcode) {
+            Op::CALL => self.frames.push(Frame {
+                addr: next_pc as u16,
+                entered_at: Instant::now(),
+                start_count: count,
+                child_count: 0,
+            }),
+            Op::RET => self.leave_routine(count),
+            _ => {}
+        }
+    }
+
+    /// Records a return from the routine entered last, such as one replaced by an intrinsic.
+    pub fn leave_routine(&mut self, count: u64) {
+        let frame = match self.frames.pop() {
+            Some(x) => x,
+            None => return,
+        };
+        let inclusive = count - frame.start_count;
+        let stats = self.routines.entry(frame.addr).or_default();
+
+        stats.calls += 1;
+        stats.inclusive_count += inclusive;
+        stats.exclusive_count += inclusive - frame.child_count;
+        stats.inclusive_time += frame.entered_at.elapsed();
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.child_count += inclusive;
+        }
+    }
+
+    /// Forgets the subroutines which have not returned yet. Used when the VM is restored to an
+    /// earlier state, where they may never return, and where fewer instructions were executed than
+    /// when they were entered.
+    pub fn clear_frames(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Builds a report of the recorded executions, sorted from hottest to coldest, using the VM
+    /// to disassemble each address.
+    pub fn report(&self, vm: &VirtualMachine) -> ProfileReport {
+        let mut addresses = self
+            .addr_counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(addr, &count)| AddressProfile {
+                addr: addr as u16,
+                count,
+                instruction: vm.disassemble_instruction(addr),
+            })
+            .collect::<Vec<_>>();
+        addresses.sort_by(|a, b| b.count.cmp(&a.count).then(a.addr.cmp(&b.addr)));
+
+        let mut opcodes = self
+            .op_counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(opcode, &count)| OpcodeProfile {
+                op: Op::from_u16(opcode as u16).to_string().trim().into(),
+                count,
+            })
+            .collect::<Vec<_>>();
+        opcodes.sort_by_key(|o| Reverse(o.count));
+
+        let mut routines = self
+            .routines
+            .iter()
+            .map(|(&addr, stats)| RoutineProfile {
+                addr,
+                calls: stats.calls,
+                inclusive_instructions: stats.inclusive_count,
+                exclusive_instructions: stats.exclusive_count,
+                inclusive_secs: stats.inclusive_time.as_secs_f64(),
+                instruction: vm.disassemble_instruction(addr as usize),
+            })
+            .collect::<Vec<_>>();
+        routines.sort_by(|a, b| {
+            b.inclusive_instructions
+                .cmp(&a.inclusive_instructions)
+                .then(a.addr.cmp(&b.addr))
+        });
+
+        ProfileReport {
+            instructions: self.total,
+            addresses,
+            opcodes,
+            routines,
+        }
+    }
+}
+
+/// Executions of a single address.
+#[derive(Serialize)]
+pub struct AddressProfile {
+    pub addr: u16,
+    pub count: u64,
+    pub instruction: String,
+}
+
+/// Executions of a single opcode.
+#[derive(Serialize)]
+pub struct OpcodeProfile {
+    pub op: String,
+    pub count: u64,
+}
+
+/// Costs of every completed call to a subroutine, identified by its entry address.
+#[derive(Serialize)]
+pub struct RoutineProfile {
+    pub addr: u16,
+    pub calls: u64,
+    /// Instructions executed by the subroutine, including those of nested calls.
+    pub inclusive_instructions: u64,
+    /// Instructions executed by the subroutine itself, excluding those of nested calls.
+    pub exclusive_instructions: u64,
+    pub inclusive_secs: f64,
+    pub instruction: String,
+}
+
+/// A profile of the executions recorded by the VirtualMachine, sorted from hottest to coldest.
+#[derive(Serialize)]
+pub struct ProfileReport {
+    pub instructions: u64,
+    pub addresses: Vec<AddressProfile>,
+    pub opcodes: Vec<OpcodeProfile>,
+    pub routines: Vec<RoutineProfile>,
+}
+
+impl ProfileReport {
+    /// Renders the report as text tables.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let total = self.instructions.max(1) as f64;
+
+        let _ = writeln!(out, "Instructions executed: {}", self.instructions);
+
+        let _ = writeln!(out, "\nSubroutines:");
+        let _ = writeln!(
+            out,
+            "{:>10}  {:>12}  {:>12}  {:>10}  address\tinstruction",
+            "calls", "inclusive", "exclusive", "seconds"
+        );
+        for r in self.routines.iter() {
+            let _ = writeln!(
+                out,
+                "{:>10}  {:>12}  {:>12}  {:>10.4}  {:#06x}:\t{}",
+                r.calls,
+                r.inclusive_instructions,
+                r.exclusive_instructions,
+                r.inclusive_secs,
+                r.addr,
+                r.instruction
+            );
+        }
+
+        let _ = writeln!(out, "\nOpcodes:");
+        let _ = writeln!(out, "{:>12}  {:>7}  op", "count", "percent");
+        for o in self.opcodes.iter() {
+            let percent = o.count as f64 * 100.0 / total;
+            let _ = writeln!(out, "{:>12}  {:>6.2}%  {}", o.count, percent, o.op);
+        }
+
+        let _ = writeln!(out, "\nAddresses:");
+        let _ = writeln!(
+            out,
+            "{:>12}  {:>7}  address\tinstruction",
+            "count", "percent"
+        );
+        for a in self.addresses.iter() {
+            let percent = a.count as f64 * 100.0 / total;
+            let _ = writeln!(
+                out,
+                "{:>12}  {:>6.2}%  {:#06x}:\t{}",
+                a.count, percent, a.addr, a.instruction
+            );
+        }
+        out
+    }
+
+    /// Renders the report as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Writes the report to a file, as JSON if the path has a `.json` extension and as text
+    /// otherwise.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let report = match path.as_ref().extension() {
+            Some(ext) if ext == "json" => self.to_json(),
+            _ => self.to_text(),
+        };
+
+        fs::write(path, report)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::vm::memory::Memory;
+
+/// A memory scan, narrowing down the addresses which may hold a game variable by comparing their
+/// values as the game state moves on.
+pub(crate) struct MemoryScan {
+    /// The candidate addresses, each with the value it held when last compared.
+    candidates: Vec<(u16, u16)>,
+}
+
+impl MemoryScan {
+    /// Starts a scan with every address whose value satisfies `predicate` as a candidate.
+    pub fn new<P>(mem: &Memory, predicate: P) -> Self
+    where
+        P: Fn(u16) -> bool,
+    {
+        MemoryScan {
+            candidates: mem
+                .iter()
+                .enumerate()
+                .filter(|&(_, &val)| predicate(val))
+                .map(|(addr, &val)| (addr as u16, val))
+                .collect(),
+        }
+    }
+
+    /// Keeps the candidates for which `predicate` holds given the value they held when last
+    /// compared and the value they hold now, and remembers their current values.
+    pub fn narrow<P>(&mut self, mem: &Memory, predicate: P)
+    where
+        P: Fn(u16, u16) -> bool,
+    {
+        self.candidates = self
+            .candidates
+            .iter()
+            .filter_map(|&(addr, old)| mem.get(addr as usize).map(|&new| (addr, old, new)))
+            .filter(|&(_, old, new)| predicate(old, new))
+            .map(|(addr, _, new)| (addr, new))
+            .collect();
+    }
+
+    /// Gets the candidate addresses, each with the value it held when last compared.
+    pub fn candidates(&self) -> &[(u16, u16)] {
+        &self.candidates
+    }
+}
@@ -0,0 +1,213 @@
+use crate::vm::{coverage::AddrSet, memory::Memory, op::Op, WriteRecord};
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+/// The furthest, in instructions, the end of a basic block is searched for.
+const MAX_BLOCK_LEN: usize = 0x100;
+
+/// How the VirtualMachine reacts to code modifying itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelfModifyMode {
+    /// Print a warning the first time an instruction modifies a given address.
+    Warn,
+    /// Stop execution on every write modifying code.
+    Break,
+}
+
+/// A write to memory which was previously executed, or which sits inside the basic block being
+/// executed.
+#[derive(Clone, Copy, Debug)]
+pub struct SelfModification {
+    /// The modified memory address.
+    pub addr: u16,
+    /// The address of the instruction which made the write.
+    pub pc: usize,
+    pub old: u16,
+    pub new: u16,
+    /// Whether the address was previously executed, rather than only being part of the current
+    /// basic block.
+    pub executed: bool,
+}
+
+impl fmt::Display for SelfModification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Code at {:#06x} modified by {:#06x}: {:#06x} -> {:#06x} ({})",
+            self.addr,
+            self.pc,
+            self.old,
+            self.new,
+            if self.executed {
+                "previously executed"
+            } else {
+                "current basic block"
+            }
+        )
+    }
+}
+
+/// A range of contiguous addresses modified by the same instruction.
+#[derive(Clone, Copy, Debug)]
+pub struct SelfModifiedRegion {
+    pub start: u16,
+    pub end: u16,
+    /// The address of the instruction which modified the region.
+    pub pc: u16,
+    /// The number of writes made to the region.
+    pub writes: u64,
+}
+
+/// Every region of code modified while the VirtualMachine ran.
+#[derive(Clone, Debug)]
+pub struct SelfModifyReport {
+    pub regions: Vec<SelfModifiedRegion>,
+}
+
+impl SelfModifyReport {
+    /// Writes the report to a file.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for SelfModifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Self-modified regions: {}", self.regions.len())?;
+
+        for region in &self.regions {
+            writeln!(
+                f,
+                "{:#06x}-{:#06x}  written by {:#06x}  ({} writes)",
+                region.start, region.end, region.pc, region.writes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Tracks executed addresses and basic blocks to find instructions writing over code.
+pub(crate) struct SelfModifyDetector {
+    mode: SelfModifyMode,
+    executed: AddrSet,
+    block_start: usize,
+    next_in_block: Option<usize>,
+    writes: BTreeMap<(u16, u16), u64>,
+}
+
+impl SelfModifyDetector {
+    pub fn new(mode: SelfModifyMode) -> Self {
+        SelfModifyDetector {
+            mode,
+            executed: AddrSet::new(),
+            block_start: 0,
+            next_in_block: None,
+            writes: BTreeMap::new(),
+        }
+    }
+
+    pub fn mode(&self) -> SelfModifyMode {
+        self.mode
+    }
+
+    /// Records the instruction executed at `pc` along with the writes it made. Returns the code
+    /// modification to report, if any; in `Warn` mode, each address is only reported the first
+    /// time an instruction modifies it. Writes made by intrinsics are not the instruction's own, so
+    /// they are not reported.
+    pub fn record(
+        &mut self,
+        mem: &Memory,
+        pc: usize,
+        writes: &[WriteRecord],
+    ) -> Option<SelfModification> {
+        let op = Op::from_u16(mem[pc]);
+        let next = pc + 1 + op.arg_count();
+
+        if self.next_in_block != Some(pc) {
+            self.block_start = pc;
+        }
+
+        let modification = writes
+            .iter()
+            .filter(|w| !w.native && (w.addr as usize) < mem.len())
+            .filter_map(|w| self.check_write(mem, pc, w))
+            .last();
+
+        for addr in pc..next {
+            self.executed.insert(addr);
+        }
+        self.next_in_block = match op {
+            Op::JMP | Op::JT | Op::JF | Op::CALL | Op::RET | Op::HALT => None,
+            _ => Some(next),
+        };
+
+        modification
+    }
+
+    /// Gets every modified region, ordered by the address of the instruction modifying it.
+    pub fn report(&self) -> SelfModifyReport {
+        let mut regions: Vec<SelfModifiedRegion> = Vec::new();
+
+        for (&(pc, addr), &count) in &self.writes {
+            match regions.last_mut() {
+                Some(region) if region.pc == pc && region.end + 1 == addr => {
+                    region.end = addr;
+                    region.writes += count;
+                }
+                _ => regions.push(SelfModifiedRegion {
+                    start: addr,
+                    end: addr,
+                    pc,
+                    writes: count,
+                }),
+            }
+        }
+        SelfModifyReport { regions }
+    }
+
+    fn check_write(
+        &mut self,
+        mem: &Memory,
+        pc: usize,
+        write: &WriteRecord,
+    ) -> Option<SelfModification> {
+        let addr = write.addr as usize;
+        let executed = self.executed.contains(addr);
+
+        if !executed && !(self.block_start..=self.block_end(mem, pc)).contains(&addr) {
+            return None;
+        }
+
+        let count = self.writes.entry((pc as u16, write.addr)).or_insert(0);
+        *count += 1;
+
+        if self.mode == SelfModifyMode::Warn && *count > 1 {
+            return None;
+        }
+        Some(SelfModification {
+            addr: write.addr,
+            pc,
+            old: write.old,
+            new: write.new,
+            executed,
+        })
+    }
+
+    /// Finds the last address of the basic block containing the instruction at `pc`.
+    fn block_end(&self, mem: &Memory, pc: usize) -> usize {
+        let mut addr = pc;
+
+        for _ in 0..MAX_BLOCK_LEN {
+            let op = match mem.get(addr) {
+                Some(&x) if Op::is_op(x) => Op::from_u16(x),
+                _ => break,
+            };
+            let next = addr + 1 + op.arg_count();
+
+            match op {
+                Op::JMP | Op::JT | Op::JF | Op::CALL | Op::RET | Op::HALT => return next - 1,
+                _ => addr = next,
+            }
+        }
+        addr
+    }
+}
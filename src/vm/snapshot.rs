@@ -0,0 +1,26 @@
+use crate::vm::{input_buffer::InputSnapshot, memory::Memory, Registers, Stack};
+
+/// A copy of the state of the VirtualMachine, taken with `VirtualMachine::snapshot` and put back
+/// with `VirtualMachine::restore`. Snapshots live in memory only, and may be restored any number
+/// of times. Taking a snapshot shares the pages of memory with the VM rather than copying them.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub(crate) mem: Memory,
+    pub(crate) reg: Registers,
+    pub(crate) stack: Stack,
+    pub(crate) pc: usize,
+    pub(crate) input: InputSnapshot,
+    pub(crate) executed: u64,
+}
+
+impl Snapshot {
+    /// Gets the program counter the snapshot was taken at.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Gets the number of instructions executed when the snapshot was taken.
+    pub fn instruction_count(&self) -> u64 {
+        self.executed
+    }
+}
@@ -0,0 +1,851 @@
+use crate::{
+    error::Error,
+    vm::{
+        io::{ChainInput, InputSource, MemoryInput, MemoryOutput},
+        ReverseOutcome, StepOutcome, VirtualMachine,
+    },
+};
+use std::{fs, io};
+
+const AX: u16 = 0x8000;
+const BX: u16 = 0x8001;
+const CX: u16 = 0x8002;
+
+/// Converts a program to little endian bytecode.
+fn bytecode(program: &[u16]) -> Vec<u8> {
+    program.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// Builds a VM running `program` from address 0, with the given input and its output discarded.
+fn vm_with_input(program: &[u16], input: &str) -> VirtualMachine {
+    VirtualMachine::new()
+        .with_input(MemoryInput::new(input))
+        .with_output(MemoryOutput::new())
+        .load_bytecode(&bytecode(program))
+        .unwrap()
+}
+
+fn vm_with(program: &[u16]) -> VirtualMachine {
+    vm_with_input(program, "")
+}
+
+/// Steps the VM `n` times, failing if any step does not simply carry on.
+fn step_n(vm: &mut VirtualMachine, n: usize) {
+    for _ in 0..n {
+        match vm.step() {
+            StepOutcome::Continued | StepOutcome::Output(_) => {}
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+}
+
+#[test]
+fn halt() {
+    let mut vm = vm_with(&[0]);
+
+    assert!(matches!(vm.step(), StepOutcome::Halted));
+}
+
+#[test]
+fn set() {
+    let mut vm = vm_with(&[1, AX, 42, 1, BX, AX]);
+
+    step_n(&mut vm, 2);
+    assert_eq!(vm.reg[0], 42);
+    assert_eq!(vm.reg[1], 42);
+    assert_eq!(vm.pc, 6);
+}
+
+#[test]
+fn push_and_pop() {
+    let mut vm = vm_with(&[2, 7, 1, AX, 9, 2, AX, 3, BX, 3, CX]);
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.stack, vec![7, 9]);
+    step_n(&mut vm, 2);
+    assert_eq!(vm.reg[1], 9);
+    assert_eq!(vm.reg[2], 7);
+    assert!(vm.stack.is_empty());
+}
+
+#[test]
+fn pop_on_empty_stack_fails() {
+    let mut vm = vm_with(&[3, AX]);
+
+    assert!(matches!(vm.step(), StepOutcome::Error(_)));
+}
+
+#[test]
+fn eq_and_gt() {
+    let mut vm = vm_with(&[4, AX, 3, 3, 4, BX, 3, 4, 5, CX, 4, 3]);
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[..3], [1, 0, 1]);
+
+    let mut vm = vm_with(&[5, AX, 3, 3, 5, BX, 3, 4]);
+    step_n(&mut vm, 2);
+    assert_eq!(vm.reg[..2], [0, 0]);
+}
+
+#[test]
+fn jumps() {
+    let mut vm = vm_with(&[6, 10]);
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 10);
+
+    let mut vm = vm_with(&[7, 1, 10]);
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 10);
+
+    let mut vm = vm_with(&[7, 0, 10]);
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 3);
+
+    let mut vm = vm_with(&[8, 0, 10]);
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 10);
+
+    let mut vm = vm_with(&[8, 1, 10]);
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 3);
+}
+
+#[test]
+fn arithmetic_wraps_at_15_bits() {
+    let mut vm = vm_with(&[
+        9, AX, 32758, 15, // add
+        10, BX, 16384, 3, // mult
+        11, CX, 17, 5, // mod
+    ]);
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[..3], [5, 16384, 2]);
+}
+
+#[test]
+fn bitwise() {
+    let mut vm = vm_with(&[12, AX, 0b1100, 0b1010, 13, BX, 0b1100, 0b1010, 14, CX, 0]);
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[..3], [0b1000, 0b1110, 0x7fff]);
+}
+
+#[test]
+fn rmem_and_wmem() {
+    let mut vm = vm_with(&[
+        16, 100, 1234, 15, AX, 100, 1, BX, 101, 16, BX, 77, 15, CX, 101,
+    ]);
+
+    step_n(&mut vm, 2);
+    assert_eq!(vm.mem[100], 1234);
+    assert_eq!(vm.reg[0], 1234);
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.mem[101], 77);
+    assert_eq!(vm.reg[2], 77);
+}
+
+#[test]
+fn call_and_ret() {
+    let mut vm = vm_with(&[17, 10, 0, 0, 0, 0, 0, 0, 0, 0, 18]);
+
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 10);
+    assert_eq!(vm.stack, vec![2]);
+
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 2);
+    assert!(vm.stack.is_empty());
+    assert!(matches!(vm.step(), StepOutcome::Halted));
+}
+
+#[test]
+fn ret_on_empty_stack_halts() {
+    let mut vm = vm_with(&[18]);
+
+    assert!(matches!(vm.step(), StepOutcome::RetOnEmptyStack));
+}
+
+#[test]
+fn out_writes_to_the_output_sink() {
+    let output = MemoryOutput::new();
+    let mut vm = VirtualMachine::new()
+        .with_output(output.clone())
+        .load_bytecode(&bytecode(&[19, 'h' as u16, 1, AX, 'i' as u16, 19, AX, 0]))
+        .unwrap();
+
+    assert!(matches!(vm.step(), StepOutcome::Output('h')));
+    step_n(&mut vm, 1);
+    assert!(matches!(vm.step(), StepOutcome::Output('i')));
+    assert_eq!(output.take(), "hi");
+}
+
+#[test]
+fn in_reads_from_the_input_source() {
+    let mut vm = vm_with_input(&[20, AX, 20, BX, 20, CX, 20, AX], "ok\n");
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[..3], ['o' as u16, 'k' as u16, '\n' as u16]);
+    assert!(matches!(vm.step(), StepOutcome::WaitingForInput));
+    assert_eq!(vm.pc, 6);
+}
+
+#[test]
+fn noop() {
+    let mut vm = vm_with(&[21, 0]);
+
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 1);
+    assert!(matches!(vm.step(), StepOutcome::Halted));
+}
+
+#[test]
+fn breakpoints_stop_before_the_instruction_once() {
+    let mut vm = vm_with(&[21, 21, 6, 0]);
+    vm.breakpoints_mut().add(1);
+
+    step_n(&mut vm, 1);
+    assert!(matches!(vm.step(), StepOutcome::Breakpoint(1)));
+    assert_eq!(vm.pc, 1);
+    step_n(&mut vm, 3);
+    assert!(matches!(vm.step(), StepOutcome::Breakpoint(1)));
+    assert_eq!(vm.breakpoints().get(1).unwrap().hits, 2);
+
+    vm.breakpoints_mut().disable(1);
+    step_n(&mut vm, 6);
+    assert_eq!(vm.pc, 1);
+}
+
+#[test]
+fn watchpoints_stop_after_the_watched_access() {
+    use crate::vm::debugger::WatchKind;
+
+    let mut vm = vm_with(&[1, AX, 5, 1, BX, 0, 1, BX, 7, 15, CX, 100, 0]);
+    vm.watchpoints_mut().add(BX, WatchKind::Change);
+    vm.watchpoints_mut().add(100, WatchKind::Read);
+
+    step_n(&mut vm, 2);
+    match vm.step() {
+        StepOutcome::Watchpoint(hit) => {
+            assert_eq!((hit.addr, hit.kind, hit.pc), (BX, WatchKind::Change, 6));
+            assert_eq!((hit.old, hit.new), (0, 7));
+        }
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert_eq!(vm.reg[1], 7);
+
+    match vm.step() {
+        StepOutcome::Watchpoint(hit) => assert_eq!((hit.addr, hit.kind), (100, WatchKind::Read)),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+}
+
+#[test]
+fn step_back_restores_memory_registers_and_stack() {
+    let mut vm = vm_with(&[16, 100, 5, 1, AX, 7, 2, AX, 3, BX, 0]);
+    vm.record_undo_log(16);
+
+    step_n(&mut vm, 4);
+    assert_eq!(vm.mem[100], 5);
+    assert_eq!(vm.reg[..2], [7, 7]);
+
+    assert!(vm.step_back());
+    assert_eq!(vm.reg[1], 0);
+    assert_eq!(vm.stack, vec![7]);
+    assert!(vm.step_back());
+    assert!(vm.stack.is_empty());
+    assert!(vm.step_back());
+    assert_eq!(vm.reg[0], 0);
+    assert!(vm.step_back());
+    assert_eq!(vm.mem[100], 0);
+    assert_eq!(vm.pc, 0);
+    assert!(!vm.step_back());
+}
+
+#[test]
+fn reverse_continue_stops_at_breakpoints() {
+    let mut vm = vm_with(&[16, 100, 5, 1, AX, 7, 16, 100, 6, 0]);
+    vm.record_undo_log(16);
+    vm.breakpoints_mut().add(3);
+
+    assert!(matches!(vm.step(), StepOutcome::Continued));
+    assert!(matches!(vm.step(), StepOutcome::Breakpoint(3)));
+    step_n(&mut vm, 2);
+    assert_eq!(vm.mem[100], 6);
+
+    assert!(matches!(
+        vm.reverse_continue(),
+        ReverseOutcome::Breakpoint(3)
+    ));
+    assert_eq!(vm.mem[100], 5);
+    assert_eq!(vm.reg[0], 0);
+    assert!(matches!(
+        vm.reverse_continue(),
+        ReverseOutcome::HistoryExhausted
+    ));
+    assert_eq!(vm.mem[100], 0);
+}
+
+#[test]
+fn runs_stop_when_the_instruction_budget_is_spent() {
+    use crate::vm::budget::Budget;
+
+    let mut vm = vm_with(&[21, 6, 0]);
+    vm.set_fuel(Some(5));
+
+    match vm.run_until(|_| false) {
+        StepOutcome::BudgetExhausted(budget) => assert_eq!(budget, Budget::Instructions),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert_eq!(vm.instruction_count(), 5);
+    assert_eq!(vm.pc, 1);
+
+    vm.set_fuel(Some(1));
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 0);
+    assert!(matches!(vm.step(), StepOutcome::BudgetExhausted(_)));
+}
+
+#[test]
+fn runs_stop_when_the_time_limit_is_up() {
+    use crate::vm::budget::Budget;
+    use std::time::Duration;
+
+    let mut vm = vm_with(&[6, 0]);
+    vm.set_time_limit(Some(Duration::from_millis(10)));
+
+    match vm.run_until(|_| false) {
+        StepOutcome::BudgetExhausted(budget) => assert_eq!(budget, Budget::Time),
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+    assert!(vm.instruction_count() > 0);
+    assert_eq!(vm.instruction_count() % 0x400, 0);
+
+    // The clock only runs during a run, not while stepping.
+    step_n(&mut vm, 1);
+}
+
+#[test]
+fn call_graph_counts_each_call_site_per_caller() {
+    let mut vm = vm_with(&[17, 6, 17, 6, 0, 0, 17, 9, 18, 18]);
+    vm.start_call_graph();
+
+    step_n(&mut vm, 8);
+    assert!(matches!(vm.step(), StepOutcome::Halted));
+
+    let edges = vm
+        .call_graph()
+        .unwrap()
+        .edges()
+        .map(|e| (e.caller, e.site, e.target, e.count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        edges,
+        vec![(None, 0, 6, 1), (None, 2, 6, 1), (Some(6), 6, 9, 2)]
+    );
+}
+
+#[test]
+fn coverage_marks_executed_read_and_written_addresses() {
+    let mut vm = vm_with(&[16, 63, 1, 15, AX, 64, 16, 64, AX, 0]);
+    vm.start_coverage();
+
+    step_n(&mut vm, 3);
+    assert!(matches!(vm.step(), StepOutcome::Halted));
+
+    let coverage = vm.coverage().unwrap();
+    assert_eq!(
+        (0..10)
+            .filter(|&x| coverage.is_executed(x))
+            .collect::<Vec<_>>(),
+        vec![0, 3, 6, 9]
+    );
+    assert_eq!(coverage.executed_count(), 4);
+    assert!(coverage.is_read(64));
+    assert_eq!(coverage.read_count(), 1);
+    assert!(coverage.is_written(63) && coverage.is_written(64));
+    assert_eq!(coverage.written_count(), 2);
+}
+
+#[test]
+fn writes_over_code_are_reported_as_self_modification() {
+    use crate::vm::self_modify::SelfModifyMode;
+
+    let mut vm = vm_with(&[21, 16, 0, 0, 16, 100, 5, 16, 11, 21, 21, 0]);
+    vm.start_self_modify_detection(SelfModifyMode::Break);
+
+    step_n(&mut vm, 1);
+    match vm.step() {
+        StepOutcome::SelfModified(m) => {
+            assert_eq!(
+                (m.addr, m.pc, m.old, m.new, m.executed),
+                (0, 1, 21, 0, true)
+            )
+        }
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+
+    // Data is not code, but the end of the running block is.
+    step_n(&mut vm, 1);
+    match vm.step() {
+        StepOutcome::SelfModified(m) => {
+            assert_eq!((m.addr, m.pc, m.executed), (11, 7, false))
+        }
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+
+    let report = vm.self_modify_report().unwrap();
+    assert_eq!(
+        report
+            .regions
+            .iter()
+            .map(|r| (r.pc, r.start, r.end, r.writes))
+            .collect::<Vec<_>>(),
+        vec![(1, 0, 0, 1), (7, 11, 11, 1)]
+    );
+}
+
+#[test]
+fn writes_from_commands_and_intrinsics_are_not_self_modification() {
+    use crate::vm::{intrinsic::IntrinsicAction, self_modify::SelfModifyMode};
+
+    let mut vm = vm_with(&[21, 17, 5, 21, 0, 18]);
+    vm.register_intrinsic(5, |guest| {
+        guest.write_mem(1, 21).unwrap();
+        IntrinsicAction::Return
+    });
+    vm.start_self_modify_detection(SelfModifyMode::Break);
+
+    step_n(&mut vm, 1);
+    run_command(&mut vm, "poke 0 0\n");
+    step_n(&mut vm, 2);
+    assert_eq!(vm.pc, 4);
+    assert!(vm.self_modify_report().unwrap().regions.is_empty());
+}
+
+#[test]
+fn self_modification_is_warned_about_once_per_address() {
+    use crate::vm::self_modify::SelfModifyMode;
+
+    let output = MemoryOutput::new();
+    let mut vm = VirtualMachine::new()
+        .with_output(output.clone())
+        .load_bytecode(&bytecode(&[21, 9, AX, AX, 1, 16, 0, AX, 6, 1]))
+        .unwrap();
+    vm.start_self_modify_detection(SelfModifyMode::Warn);
+
+    step_n(&mut vm, 3);
+    assert!(output.take().starts_with("Warning: "));
+    step_n(&mut vm, 3);
+    assert_eq!(vm.mem[0], 2);
+    assert_eq!(output.take(), "");
+}
+
+/// Steps a strict VM running `program` once, returning the error it fails with.
+fn strict_error(program: &[u16]) -> Error {
+    let mut vm = vm_with(program).with_strict(true);
+
+    match vm.step() {
+        StepOutcome::Error(e) => e,
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+}
+
+#[test]
+fn strict_mode_rejects_invalid_literals() {
+    assert!(matches!(
+        strict_error(&[1, AX, 0x8008]),
+        Error::InvalidLiteral {
+            pc: 2,
+            value: 0x8008
+        }
+    ));
+
+    let mut vm = vm_with(&[1, AX, 0x8008]);
+    step_n(&mut vm, 1);
+    assert_eq!(vm.reg[0], 0x8008);
+}
+
+#[test]
+fn strict_mode_rejects_output_outside_of_ascii() {
+    assert!(matches!(
+        strict_error(&[19, 0x100]),
+        Error::NonAsciiOutput {
+            pc: 0,
+            value: 0x100
+        }
+    ));
+    assert!(matches!(
+        strict_error(&[19, 0x80]),
+        Error::NonAsciiOutput { pc: 0, value: 0x80 }
+    ));
+
+    let mut vm = vm_with(&[19, 0x7f]).with_strict(true);
+    assert!(matches!(vm.step(), StepOutcome::Output('\x7f')));
+}
+
+#[test]
+fn strict_mode_rejects_ret_and_pop_on_an_empty_stack() {
+    assert!(matches!(
+        strict_error(&[18]),
+        Error::StackUnderflowOnRet { pc: 0 }
+    ));
+    assert!(matches!(
+        strict_error(&[3, AX]),
+        Error::PopFromEmptyStack { .. }
+    ));
+}
+
+#[test]
+fn division_by_zero_fails() {
+    let mut vm = vm_with(&[11, AX, 5, 0]);
+
+    assert!(matches!(
+        vm.step(),
+        StepOutcome::Error(Error::DivisionByZero { pc: 0 })
+    ));
+}
+
+#[test]
+fn writes_to_a_fork_leave_the_original_alone() {
+    let mut vm = vm_with(&[16, 100, 5, 0]);
+    let mut fork = vm.clone();
+
+    step_n(&mut fork, 1);
+    assert_eq!(fork.mem[100], 5);
+    assert_eq!(vm.mem[100], 0);
+
+    step_n(&mut vm, 1);
+    assert_eq!(vm.mem[100], 5);
+    assert_eq!(fork.pc, vm.pc);
+}
+
+/// Input typed by a person, simulated with lines from memory.
+struct Typed(MemoryInput);
+
+impl InputSource for Typed {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.0.read_line(buf)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn chained_input_reads_the_script_before_switching_to_the_player() {
+    let mut input = ChainInput::new(
+        MemoryInput::new("take tablet\nlook\n"),
+        Typed(MemoryInput::new("go north\n")),
+    );
+    let mut lines = vec![];
+
+    loop {
+        let mut line = String::new();
+        let interactive = input.is_interactive();
+        if input.read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        lines.push((line, interactive));
+    }
+    assert_eq!(
+        lines,
+        vec![
+            ("take tablet\n".to_string(), false),
+            ("look\n".to_string(), false),
+            ("go north\n".to_string(), true),
+        ]
+    );
+}
+
+#[test]
+fn the_prompt_is_shown_from_the_first_line_the_player_types() {
+    let output = MemoryOutput::new();
+    let input = ChainInput::new(MemoryInput::new("a\n"), Typed(MemoryInput::new("b\n")));
+    let mut vm = VirtualMachine::new()
+        .with_input(input)
+        .with_output(output.clone())
+        .load_bytecode(&bytecode(&[20, AX, 20, AX, 20, BX, 20, BX]))
+        .unwrap();
+
+    step_n(&mut vm, 2);
+    assert_eq!(output.take(), "");
+    step_n(&mut vm, 1);
+    assert_eq!(output.take(), "> ");
+    assert_eq!(vm.reg[1], 'b' as u16);
+}
+
+#[test]
+fn transcripts_replay_the_recorded_input() {
+    use crate::vm::transcript::TranscriptInput;
+
+    let path = std::env::temp_dir().join(format!("synacor-vm-test-{}.txt", std::process::id()));
+    let program = [20, AX, 20, AX, 20, AX, 20, BX, 20, BX, 20, BX, 0];
+
+    let mut vm = vm_with_input(&program, "hi\nyo\n");
+    vm.record_transcript(&path).unwrap();
+    step_n(&mut vm, 6);
+    vm.stop_transcript();
+
+    let transcript = TranscriptInput::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        transcript
+            .remaining()
+            .map(|x| (x.instruction, x.line.as_str()))
+            .collect::<Vec<_>>(),
+        vec![(0, "hi"), (3, "yo")]
+    );
+
+    // An extra instruction puts the replay out of sync, which is only warned about.
+    let mut program = program.to_vec();
+    program.insert(0, 21);
+    let output = MemoryOutput::new();
+    let mut vm = VirtualMachine::new()
+        .with_input(transcript)
+        .with_output(output.clone())
+        .load_bytecode(&bytecode(&program))
+        .unwrap();
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[0], 'i' as u16);
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[1], 'o' as u16);
+    step_n(&mut vm, 1);
+    assert!(matches!(vm.step(), StepOutcome::Halted));
+    assert_eq!(
+        output.take(),
+        "Warning: transcript out of sync, \"hi\" was recorded at instruction 0 but read at 1\n\
+         Warning: transcript out of sync, \"yo\" was recorded at instruction 3 but read at 4\n"
+    );
+}
+
+#[test]
+fn scans_narrow_down_addresses_by_value_and_change() {
+    use crate::vm::scan::MemoryScan;
+
+    let mut vm = vm_with(&[16, 100, 7, 16, 101, 7, 16, 100, 8, 0]);
+    step_n(&mut vm, 2);
+
+    let mut scan = MemoryScan::new(&vm.mem, |x| x == 7);
+    assert_eq!(scan.candidates(), [(2, 7), (5, 7), (100, 7), (101, 7)]);
+
+    step_n(&mut vm, 1);
+    scan.narrow(&vm.mem, |old, new| old != new);
+    assert_eq!(scan.candidates(), [(100, 8)]);
+
+    scan.narrow(&vm.mem, |_, new| new == 7);
+    assert!(scan.candidates().is_empty());
+}
+
+#[test]
+fn restore_puts_back_state_and_instruction_count() {
+    let mut vm = vm_with(&[1, AX, 1, 16, 100, 5, 1, AX, 2, 0]);
+    let snapshot = vm.snapshot();
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.instruction_count(), 3);
+
+    vm.restore(&snapshot);
+    assert_eq!(vm.instruction_count(), 0);
+    assert_eq!(vm.reg[0], 0);
+    assert_eq!(vm.mem[100], 0);
+    assert_eq!(vm.pc, 0);
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[0], 2);
+    assert_eq!(vm.mem[100], 5);
+}
+
+#[test]
+fn wmem_over_a_cached_instruction_decodes_it_again() {
+    let mut vm = vm_with(&[9, AX, AX, 1, 16, 3, 5, 6, 0]);
+    assert!(vm.has_decode_cache());
+
+    step_n(&mut vm, 1);
+    assert_eq!(vm.reg[0], 1);
+    assert!(vm.decode_cache.as_ref().unwrap().get(0).is_some());
+
+    // Rewrite the last operand of the cached `ADD`, then jump back to it.
+    step_n(&mut vm, 2);
+    assert!(vm.decode_cache.as_ref().unwrap().get(0).is_none());
+    step_n(&mut vm, 1);
+    assert_eq!(vm.reg[0], 6);
+}
+
+#[test]
+fn fast_path_respects_the_instruction_budget() {
+    let mut vm = vm_with(&[6, 0]);
+    vm.set_fuel(Some(3));
+
+    step_n(&mut vm, 3);
+    assert!(matches!(vm.step(), StepOutcome::BudgetExhausted(_)));
+    assert_eq!(vm.instruction_count(), 3);
+}
+
+#[test]
+fn peeking_memory_has_no_side_effects() {
+    use crate::vm::debugger::WatchKind;
+
+    let mut vm = vm_with(&[21, 0]);
+    vm.start_coverage();
+    vm.watchpoints_mut().add(1, WatchKind::Read);
+
+    assert_eq!(vm.peek_mem(0), Some(21));
+    assert_eq!(vm.peek_mem(0x8000), None);
+    assert_eq!(vm.coverage().unwrap().read_count(), 0);
+    assert_eq!(vm.watchpoints().iter().next().unwrap().2.hits, 0);
+}
+
+/// Reads `line` as a shell command and runs it.
+fn run_command(vm: &mut VirtualMachine, line: &str) {
+    vm.input_buffer.set_input(Box::new(MemoryInput::new(line)));
+    let (cmd, args) = vm
+        .input_buffer
+        .read_command(&mut *vm.output, 0)
+        .unwrap()
+        .expect("not a command");
+    cmd.run(args, vm).unwrap();
+}
+
+#[test]
+fn shell_edits_are_undone_by_stepping_back() {
+    let mut vm = vm_with(&[21, 0]);
+    vm.record_undo_log(16);
+    vm.stack.push(9);
+
+    run_command(&mut vm, "push 1 2 3\n");
+    run_command(&mut vm, "pop 3\n");
+    run_command(&mut vm, "poke 100 7 8\n");
+    run_command(&mut vm, "setreg AX 5\n");
+    assert_eq!(vm.stack, vec![9]);
+    assert_eq!((vm.mem[100], vm.mem[101]), (7, 8));
+    assert_eq!(vm.reg[0], 5);
+
+    assert!(vm.step_back());
+    assert_eq!(vm.reg[0], 0);
+    assert!(vm.step_back());
+    assert_eq!((vm.mem[100], vm.mem[101]), (0, 0));
+    assert!(vm.step_back());
+    assert_eq!(vm.stack, vec![9, 1, 2, 3]);
+    assert!(vm.step_back());
+    assert_eq!(vm.stack, vec![9]);
+    assert!(!vm.step_back());
+}
+
+#[test]
+fn setpc_runs_the_instruction_under_a_breakpoint() {
+    let mut vm = vm_with(&[21, 21, 21, 0]);
+    vm.breakpoints_mut().add(2);
+
+    run_command(&mut vm, "setpc 2\n");
+    step_n(&mut vm, 1);
+    assert_eq!(vm.pc, 3);
+}
+
+#[test]
+fn only_lines_starting_with_the_prefix_are_commands_by_default() {
+    let mut vm = vm_with_input(&[20, AX, 20, BX, 20, CX], "!setreg DX 1\nexit\n");
+
+    step_n(&mut vm, 3);
+    assert_eq!(vm.reg[..4], ['e' as u16, 'x' as u16, 'i' as u16, 1]);
+}
+
+#[test]
+fn send_queues_the_rest_of_the_line_verbatim() {
+    let program = [20, AX, 20, BX, 20, CX, 20, 0x8003, 20, 0x8004];
+    let mut vm = vm_with_input(&program, "!send a  b\n");
+
+    step_n(&mut vm, 5);
+    assert_eq!(
+        vm.reg[..5],
+        ['a' as u16, ' ' as u16, ' ' as u16, 'b' as u16, '\n' as u16]
+    );
+}
+
+#[test]
+fn profiling_carries_on_across_a_restore() {
+    let mut vm = vm_with(&[17, 4, 17, 4, 21, 21, 18]);
+    vm.start_profiling();
+
+    step_n(&mut vm, 1);
+    let snapshot = vm.snapshot();
+
+    // Return, then enter the routine again before going back to the snapshot.
+    step_n(&mut vm, 4);
+    vm.restore(&snapshot);
+    step_n(&mut vm, 3);
+    assert_eq!(vm.pc, 2);
+
+    let report = vm.profile_report().unwrap();
+    assert_eq!(report.routines[0].calls, 1);
+    assert_eq!(report.routines[0].inclusive_instructions, 3);
+}
+
+#[test]
+fn trace_records_round_trip_with_any_number_of_writes() {
+    use crate::vm::trace::{TraceReader, TraceRecord, Tracer};
+
+    let path = std::env::temp_dir().join(format!("synacor-vm-trace-{}.bin", std::process::id()));
+    let records = [
+        TraceRecord {
+            pc: 0x10,
+            opcode: 1,
+            args: vec![AX, BX],
+            reg_values: vec![3, 4],
+            writes: vec![(AX, 4)],
+        },
+        TraceRecord {
+            pc: 0x13,
+            opcode: 21,
+            args: vec![],
+            reg_values: vec![],
+            writes: (0..300).map(|x| (x, x * 2)).collect(),
+        },
+    ];
+
+    let mut tracer = Tracer::create(&path).unwrap();
+    for record in records.iter() {
+        tracer.record(record).unwrap();
+    }
+    tracer.flush().unwrap();
+
+    let read = TraceReader::open(&path)
+        .unwrap()
+        .collect::<crate::Result<Vec<_>>>()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(read.len(), 2);
+    for (read, record) in read.iter().zip(records.iter()) {
+        assert_eq!(
+            (
+                read.pc,
+                read.opcode,
+                &read.args,
+                &read.reg_values,
+                &read.writes
+            ),
+            (
+                record.pc,
+                record.opcode,
+                &record.args,
+                &record.reg_values,
+                &record.writes
+            )
+        );
+    }
+}
+
+#[test]
+fn in_encoded_in_a_register_waits_for_input() {
+    let mut vm = vm_with_input(&[1, AX, 20, AX, BX, 0], "x\n");
+    vm.record_undo_log(4);
+
+    step_n(&mut vm, 2);
+    assert_eq!(vm.reg[1], 'x' as u16);
+    assert!(vm.step_back());
+    step_n(&mut vm, 1);
+    assert_eq!(vm.reg[1], 'x' as u16);
+}
@@ -0,0 +1,195 @@
+//! Execution traces recording every instruction executed by the VirtualMachine.
+//!
+//! A trace file starts with the `TRACE_MAGIC` header, followed by one record per instruction:
+//!
+//! - `pc: u16`
+//! - `opcode: u16`
+//! - one `u16` per operand of the opcode, as stored in memory
+//! - one `u16` per register operand, holding the value of the register before execution
+//! - `write_count: u16`, followed by `addr: u16, value: u16` for each register or memory write
+//!
+//! All values are little endian. As everywhere else in the VM, addresses from `0x8000` to `0x8007`
+//! refer to registers.
+
+use crate::{error::Error, vm::op::Op, Result, VirtualMachine};
+use std::{
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// The header every trace file starts with.
+pub const TRACE_MAGIC: &[u8; 8] = b"SYNTRC02";
+
+/// A single executed instruction.
+#[derive(Clone, Debug)]
+pub struct TraceRecord {
+    pub pc: u16,
+    pub opcode: u16,
+    /// The operands of the instruction, as stored in memory.
+    pub args: Vec<u16>,
+    /// The values of the register operands before execution, in operand order.
+    pub reg_values: Vec<u16>,
+    /// The `(addr, value)` pairs of every register or memory write.
+    pub writes: Vec<(u16, u16)>,
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}:\t{}", self.pc, Op::from_u16(self.opcode))?;
+        for &arg in self.args.iter() {
+            write!(f, "\t{}", Op::RegOrData(arg))?;
+        }
+
+        let regs = self.args.iter().filter(|&&x| VirtualMachine::is_reg(x));
+        for (i, (&reg, &val)) in regs.zip(self.reg_values.iter()).enumerate() {
+            let sep = if i == 0 { "\t; " } else { ", " };
+            write!(f, "{}{}={:04x}", sep, Op::RegOrData(reg), val)?;
+        }
+
+        for &(addr, val) in self.writes.iter() {
+            if VirtualMachine::is_reg(addr) {
+                write!(f, "\t=> {}={:04x}", Op::RegOrData(addr), val)?;
+            } else {
+                write!(f, "\t=> [{:04x}]={:04x}", addr, val)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes trace records to a file.
+pub(crate) struct Tracer {
+    writer: BufWriter<File>,
+}
+
+impl Tracer {
+    /// Creates a trace file at the given path.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(TRACE_MAGIC)?;
+        Ok(Tracer { writer })
+    }
+
+    /// Appends a record to the trace file.
+    pub fn record(&mut self, record: &TraceRecord) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(16);
+        let header = [record.pc, record.opcode];
+        let words = header
+            .iter()
+            .chain(record.args.iter())
+            .chain(record.reg_values.iter());
+
+        for word in words {
+            buf.extend_from_slice(&word.to_le_bytes());
+        }
+
+        let write_count = u16::try_from(record.writes.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many writes to trace"))?;
+        buf.extend_from_slice(&write_count.to_le_bytes());
+        for &(addr, val) in record.writes.iter() {
+            buf.extend_from_slice(&addr.to_le_bytes());
+            buf.extend_from_slice(&val.to_le_bytes());
+        }
+
+        self.writer.write_all(&buf)
+    }
+
+    /// Flushes any buffered records to the trace file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads trace records from a trace file.
+pub struct TraceReader<R: Read> {
+    reader: R,
+}
+
+impl TraceReader<BufReader<File>> {
+    /// Opens the trace file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        TraceReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> TraceReader<R> {
+    /// Creates a TraceReader from a reader positioned at the start of a trace.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 8];
+
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| Error::BadTraceFormat)?;
+        if &magic != TRACE_MAGIC {
+            return Err(Error::BadTraceFormat);
+        }
+
+        Ok(TraceReader { reader })
+    }
+
+    fn read_u16(&mut self) -> io::Result<u16> {
+        let mut buf = [0; 2];
+
+        self.reader.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn read_record(&mut self, pc: u16) -> io::Result<TraceRecord> {
+        let opcode = self.read_u16()?;
+        let args = (0..Op::from_u16(opcode).arg_count())
+            .map(|_| self.read_u16())
+            .collect::<io::Result<Vec<_>>>()?;
+        let reg_values = args
+            .iter()
+            .filter(|&&x| VirtualMachine::is_reg(x))
+            .map(|_| self.read_u16())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let write_count = self.read_u16()?;
+        let writes = (0..write_count)
+            .map(|_| Ok((self.read_u16()?, self.read_u16()?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(TraceRecord {
+            pc,
+            opcode,
+            args,
+            reg_values,
+            writes,
+        })
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = Result<TraceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = [0; 2];
+
+        match self.reader.read(&mut buf[..1]) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e.into())),
+        }
+        if self.reader.read_exact(&mut buf[1..]).is_err() {
+            return Some(Err(Error::BadTraceFormat));
+        }
+
+        Some(
+            self.read_record(u16::from_le_bytes(buf))
+                .map_err(|_| Error::BadTraceFormat),
+        )
+    }
+}
+
+/// Renders the trace file at the given path as text, one instruction per line.
+pub fn render<P: AsRef<Path>, W: Write>(path: P, out: &mut W) -> Result<()> {
+    for record in TraceReader::open(path)? {
+        writeln!(out, "{}", record?)?;
+    }
+    Ok(())
+}
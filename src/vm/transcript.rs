@@ -0,0 +1,116 @@
+//! Session transcripts recording every line of input given to the VirtualMachine.
+//!
+//! A transcript file starts with the `TRANSCRIPT_HEADER` line, followed by one line per line of
+//! input read, both game input and shell commands. Each holds the number of instructions executed
+//! when the line was read, a tab, and the line itself, such as `701400\ttake tablet`.
+//!
+//! As the VM is deterministic, replaying a transcript through `TranscriptInput` reproduces the
+//! recorded session exactly, provided it starts from the same program.
+
+use crate::{error::Error, vm::io::InputSource, Result};
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The first line of every transcript file.
+pub const TRANSCRIPT_HEADER: &str = "# synacor-vm transcript";
+
+/// A single line of input.
+#[derive(Clone, Debug)]
+pub struct TranscriptEntry {
+    /// The number of instructions executed when the line was read.
+    pub instruction: u64,
+    /// The line, without its trailing newline.
+    pub line: String,
+}
+
+/// Writes lines of input to a transcript file as they are read.
+pub(crate) struct TranscriptWriter {
+    writer: BufWriter<File>,
+}
+
+impl TranscriptWriter {
+    /// Creates the transcript file at the given path, truncating it if it already exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writeln!(writer, "{}", TRANSCRIPT_HEADER)?;
+        writer.flush()?;
+        Ok(TranscriptWriter { writer })
+    }
+
+    /// Appends a line read after `instruction` instructions. Every line is flushed right away, so
+    /// the transcript is complete even if the VM crashes.
+    pub fn record(&mut self, instruction: u64, line: &str) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{}\t{}",
+            instruction,
+            line.trim_end_matches('\n')
+        )?;
+        self.writer.flush()
+    }
+}
+
+/// Replays the lines of input recorded in a transcript file.
+pub struct TranscriptInput {
+    entries: VecDeque<TranscriptEntry>,
+    expected: Option<u64>,
+}
+
+impl TranscriptInput {
+    /// Reads the transcript file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+
+        if lines.next() != Some(TRANSCRIPT_HEADER) {
+            return Err(Error::BadTranscriptFormat);
+        }
+
+        let entries = lines
+            .map(|line| {
+                let mut parts = line.splitn(2, '\t');
+
+                match (parts.next().map(|x| x.parse()), parts.next()) {
+                    (Some(Ok(instruction)), Some(line)) => Ok(TranscriptEntry {
+                        instruction,
+                        line: line.into(),
+                    }),
+                    _ => Err(Error::BadTranscriptFormat),
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(TranscriptInput {
+            entries,
+            expected: None,
+        })
+    }
+
+    /// Gets the lines not replayed yet.
+    pub fn remaining(&self) -> impl Iterator<Item = &TranscriptEntry> {
+        self.entries.iter()
+    }
+}
+
+impl InputSource for TranscriptInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let entry = match self.entries.pop_front() {
+            Some(x) => x,
+            None => return Ok(0),
+        };
+
+        self.expected = Some(entry.instruction);
+        buf.push_str(&entry.line);
+        buf.push('\n');
+        Ok(entry.line.len() + 1)
+    }
+
+    fn expected_instruction_count(&self) -> Option<u64> {
+        self.expected
+    }
+}
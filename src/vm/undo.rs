@@ -0,0 +1,69 @@
+use crate::vm::{debugger::WatchHit, input_buffer::InputCursor, WriteRecord};
+use std::collections::VecDeque;
+
+/// The default number of instructions kept in the undo log.
+pub const DEFAULT_UNDO_CAPACITY: usize = 1_000_000;
+
+/// The changes made by a single instruction, or by a single edit from the shell, which are
+/// reverted to step backwards over it.
+pub(crate) struct UndoEntry {
+    /// The address of the instruction.
+    pub pc: usize,
+    /// The register and memory writes made by the instruction, in order.
+    pub writes: Vec<WriteRecord>,
+    /// The length of the stack before the instruction.
+    pub stack_len: usize,
+    /// The values popped off the stack, from the bottom up.
+    pub popped: Vec<u16>,
+    /// The state of the input buffer before an `IN` instruction.
+    pub input: Option<InputCursor>,
+    /// The watchpoint hit by the instruction, if any.
+    pub watch_hit: Option<WatchHit>,
+}
+
+/// A bounded log of the most recently executed instructions, discarding the oldest once full.
+pub(crate) struct UndoLog {
+    entries: VecDeque<UndoEntry>,
+    capacity: usize,
+}
+
+impl UndoLog {
+    /// Creates an empty undo log which holds at most `capacity` instructions.
+    pub fn new(capacity: usize) -> Self {
+        UndoLog {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Appends an entry, discarding the oldest one if the log is full.
+    pub fn push(&mut self, entry: UndoEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Removes the most recent entry.
+    pub fn pop(&mut self) -> Option<UndoEntry> {
+        self.entries.pop_back()
+    }
+
+    /// Gets the most recent entry.
+    pub fn peek(&self) -> Option<&UndoEntry> {
+        self.entries.back()
+    }
+
+    /// The number of instructions which can be stepped back over.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The maximum number of instructions the log holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
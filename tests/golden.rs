@@ -0,0 +1,23 @@
+use std::process::Command;
+
+/// Plays `data/challenge.bin` through to the end with the walkthrough script, checking the output
+/// of the game against the recorded transcript with `synacor-vm check`.
+#[test]
+fn challenge_walkthrough_matches_expected_output() {
+    let output = Command::new(env!("CARGO_BIN_EXE_synacor-vm"))
+        .args([
+            "check",
+            "data/challenge.bin",
+            "data/challenge-walkthrough.txt",
+            "data/challenge-walkthrough.out",
+        ])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
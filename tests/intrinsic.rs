@@ -0,0 +1,97 @@
+extern crate synacor_vm;
+
+use std::{cell::RefCell, rc::Rc};
+use synacor_vm::{
+    intrinsic::IntrinsicAction,
+    io::{MemoryInput, MemoryOutput},
+    teleporter, RunOutcome, VirtualMachine,
+};
+
+/// The address of the teleporter confirmation routine in `data/challenge.bin`.
+const CONFIRMATION_ROUTINE: u16 = 0x178b;
+
+/// Replaces the teleporter confirmation routine with a native implementation, and plays the
+/// walkthrough without the pokes which otherwise bypass it. Apart from the messages of the pokes
+/// themselves, the game must play out exactly as it does with the routine patched out.
+#[test]
+fn native_confirmation_routine_plays_the_walkthrough() {
+    let script = include_str!("../data/challenge-walkthrough.txt")
+        .lines()
+        .filter(|line| !line.starts_with("!poke"))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    let output = MemoryOutput::new();
+    let mut vm = VirtualMachine::new()
+        .with_input(MemoryInput::new(script))
+        .with_output(output.clone())
+        .load_bytecode(include_bytes!("../data/challenge.bin"))
+        .unwrap();
+    let calls = Rc::new(RefCell::new(vec![]));
+    let seen = calls.clone();
+
+    vm.register_intrinsic(CONFIRMATION_ROUTINE, move |guest| {
+        let reg = |idx| guest.reg(idx).unwrap();
+        seen.borrow_mut().push((reg(0), reg(1), reg(7)));
+
+        let mut mem = teleporter::Memory::new(reg(7));
+        guest.set_reg(0, teleporter::calibrate(&mut mem)).unwrap();
+        IntrinsicAction::Return
+    });
+
+    assert!(matches!(vm.run().unwrap(), RunOutcome::InputExhausted));
+    assert_eq!(*calls.borrow(), vec![(4, 1, 25734)]);
+    let expected = include_str!("../data/challenge-walkthrough.out")
+        .lines()
+        .filter(|line| !line.starts_with("Wrote "))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    assert_eq!(output.take(), expected);
+}
+
+#[test]
+fn setting_a_register_out_of_range_fails() {
+    // CALL 0x0003, HALT, RET
+    let bytecode = [17, 0, 3, 0, 0, 0, 18, 0];
+    let mut vm = VirtualMachine::new().load_bytecode(&bytecode).unwrap();
+    let results = Rc::new(RefCell::new(vec![]));
+    let seen = results.clone();
+
+    vm.register_intrinsic(3, move |guest| {
+        let mut results = seen.borrow_mut();
+        results.push(guest.set_reg(7, 1).is_ok());
+        results.push(guest.set_reg(8, 1).is_ok());
+        results.push(guest.set_reg(usize::MAX, 1).is_ok());
+        IntrinsicAction::Return
+    });
+
+    assert!(matches!(vm.run().unwrap(), RunOutcome::Halted));
+    assert_eq!(*results.borrow(), vec![true, false, false]);
+}
+
+#[test]
+fn stack_changes_are_undone_with_the_call() {
+    // PUSH 5, CALL 0x0005, HALT, RET
+    let bytecode = [2, 0, 5, 0, 17, 0, 5, 0, 0, 0, 18, 0];
+    let mut vm = VirtualMachine::new().load_bytecode(&bytecode).unwrap();
+    let stacks = Rc::new(RefCell::new(vec![]));
+    let seen = stacks.clone();
+
+    vm.register_intrinsic(5, move |guest| {
+        seen.borrow_mut().push(guest.stack().to_vec());
+        assert_eq!(guest.reg(8), None);
+
+        let ret_addr = guest.pop().unwrap();
+        guest.pop();
+        guest.push(6);
+        guest.push(7);
+        guest.push(ret_addr);
+        IntrinsicAction::Return
+    });
+    vm.record_undo_log(16);
+
+    vm.step();
+    vm.step();
+    assert!(vm.step_back());
+    vm.step();
+    assert_eq!(*stacks.borrow(), vec![vec![5, 4], vec![5, 4]]);
+}